@@ -0,0 +1,113 @@
+use std::io::Cursor;
+
+use bytemuck::pod_read_unaligned;
+
+use crate::{
+    bsp::Bsp,
+    header::{BspHeader, LUMP_ENTITIES, LUMP_MODELS, LUMP_PLANES, LUMP_VERTICES},
+    lazy::LazyBsp,
+    parsing::{
+        analysis::{HeaderAnalysis, HeaderIssue, COMPILER_LUMP_ORDER},
+        decoding::{BspLumpErrorKind, BspParseError},
+    },
+};
+
+use super::{fixture, stream::stream};
+
+fn analyze(data: &[u8]) -> HeaderAnalysis {
+    let header: BspHeader = pod_read_unaligned(&data[..std::mem::size_of::<BspHeader>()]);
+    header.analyze(data.len() as u64)
+}
+
+#[test]
+fn test_clean_header() {
+    let analysis = analyze(&fixture::map());
+    assert!(analysis.errors.is_empty());
+    assert!(analysis.warnings.is_empty());
+
+    let data = fixture::assemble_in(30, &fixture::lumps(), &COMPILER_LUMP_ORDER);
+    let analysis = analyze(&data);
+    assert!(analysis.errors.is_empty());
+    assert!(analysis.warnings.is_empty());
+}
+
+#[test]
+fn test_unusual_order() {
+    let order: Vec<usize> = (0..15).rev().collect();
+    let data = fixture::assemble_in(30, &fixture::lumps(), &order);
+    assert_eq!(analyze(&data).warnings, vec![HeaderIssue::UnusualOrder]);
+}
+
+#[test]
+fn test_fatal_errors() {
+    let mut lumps = fixture::lumps();
+    lumps[LUMP_PLANES.0].push(0);
+    let mut data = fixture::assemble(30, &lumps);
+    let file_len = data.len();
+    data.truncate(file_len - 4);
+    let analysis = analyze(&data);
+    assert!(analysis.is_fatal());
+    assert_eq!(
+        analysis.errors,
+        vec![
+            HeaderIssue::PartialElement {
+                lump: LUMP_PLANES,
+                element_size: 20
+            },
+            HeaderIssue::PastEndOfFile {
+                lump: LUMP_MODELS,
+                end: file_len as u64
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_overlap_and_gap() {
+    let mut data = fixture::map();
+    let models = 4 + LUMP_MODELS.0 * 8;
+    let offset = i32::from_le_bytes(data[models..models + 4].try_into().unwrap());
+    data[models..models + 4].copy_from_slice(&(offset + 8).to_le_bytes());
+    data.extend([0; 8]);
+    let planes = 4 + LUMP_PLANES.0 * 8;
+    let vertices = 4 + LUMP_VERTICES.0 * 8;
+    let pointer = data[planes..planes + 4].to_vec();
+    data[vertices..vertices + 4].copy_from_slice(&pointer);
+
+    let analysis = analyze(&data);
+    assert!(!analysis.is_fatal());
+    assert!(analysis.warnings.contains(&HeaderIssue::Overlap {
+        lump: LUMP_VERTICES,
+        other: LUMP_PLANES
+    }));
+    assert!(analysis
+        .warnings
+        .iter()
+        .any(|issue| matches!(issue, HeaderIssue::Gap { len: 8, .. })));
+}
+
+#[test]
+fn test_blue_shift_header() {
+    let mut lumps = fixture::lumps();
+    lumps.swap(LUMP_ENTITIES.0, LUMP_PLANES.0);
+    assert!(analyze(&fixture::assemble(30, &lumps)).errors.is_empty());
+}
+
+#[test]
+fn test_parsers_check_header() {
+    let mut data = fixture::map();
+    let offset = 4 + LUMP_MODELS.0 * 8 + 4;
+    data[offset..offset + 4].copy_from_slice(&(-1i32).to_le_bytes());
+    let results = [
+        Bsp::parse(&mut Cursor::new(data.clone())).map(|_| ()),
+        LazyBsp::open(Cursor::new(data.clone())).map(|_| ()),
+        stream(data).map(|_| ()),
+    ];
+    for result in results {
+        let Err(BspParseError::Lump(err)) = result else {
+            panic!("expected a lump error, got {result:?}");
+        };
+        assert_eq!(err.lump, LUMP_MODELS);
+        assert!(matches!(err.kind, BspLumpErrorKind::NegativePointer));
+    }
+}
@@ -0,0 +1,105 @@
+use std::io::Cursor;
+
+use futures::{executor::block_on, io::Cursor as AsyncCursor};
+
+use crate::{
+    bsp::Bsp,
+    header::{BspHeader, BspLumpLayout, LUMP_ENTITIES, LUMP_LEAVES, LUMP_PLANES},
+    lumps::{bspx::BSPX_LMSHIFT, faces::BspFacesLump, vertices::BspVerticesLump},
+    parsing::{
+        decoding::BspParseError,
+        options::{ParseLimitError, ParseOptions},
+    },
+};
+
+use super::{bspx::with_bspx, fixture, options::with_repeated_bspx};
+
+#[test]
+fn test_parse_async() {
+    let data = with_bspx(fixture::map(), &[(BSPX_LMSHIFT, vec![4])]);
+    let bsp = block_on(Bsp::parse_async(&mut AsyncCursor::new(&data))).unwrap();
+    assert_eq!(bsp.entities.0.len(), 2);
+    assert_eq!(
+        bsp.bspx.as_ref().unwrap().lm_shift.as_ref().unwrap().0,
+        vec![4]
+    );
+    assert_eq!(fixture::written(&bsp), data);
+
+    let mut lumps = fixture::lumps();
+    lumps.swap(LUMP_ENTITIES.0, LUMP_PLANES.0);
+    let data = fixture::assemble(30, &lumps);
+    let bsp = block_on(Bsp::parse_async(&mut AsyncCursor::new(&data))).unwrap();
+    assert_eq!(bsp.layout, BspLumpLayout::BlueShift);
+    assert_eq!(fixture::written(&bsp), data);
+}
+
+#[test]
+fn test_extract_lump_async() {
+    let data = fixture::map();
+    let mut read = AsyncCursor::new(&data);
+    let bsp = Bsp::parse(&mut Cursor::new(&data)).unwrap();
+    let vertices: BspVerticesLump = block_on(bsp.header.extract_lump_async(&mut read)).unwrap();
+    assert_eq!(format!("{:?}", vertices.0), format!("{:?}", bsp.vertices.0));
+    let faces: BspFacesLump = block_on(bsp.header.extract_lump_async(&mut read)).unwrap();
+    assert_eq!(faces.0.len(), bsp.faces.0.len());
+}
+
+#[test]
+fn test_parse_async_truncated() {
+    let mut data = fixture::map();
+    data.truncate(data.len() - 8);
+    let result = block_on(Bsp::parse_async(&mut AsyncCursor::new(&data)));
+    assert!(matches!(result, Err(BspParseError::Lump(_))));
+}
+
+#[test]
+fn test_parse_async_bspx() {
+    let mut data = with_bspx(
+        fixture::map(),
+        &[(BSPX_LMSHIFT, vec![4]), ("CUSTOM", vec![1; 8])],
+    );
+    data.truncate(data.len() - 4);
+    let bsp = block_on(Bsp::parse_async(&mut AsyncCursor::new(&data))).unwrap();
+    let parsed = Bsp::parse(&mut Cursor::new(&data)).unwrap();
+    let bspx = bsp.bspx.unwrap();
+    assert_eq!(bspx.lm_shift.unwrap().0, vec![4]);
+    assert_eq!(bspx.errors.len(), 1);
+    assert_eq!(bspx.errors[0].name, "CUSTOM");
+    assert_eq!(
+        format!("{:?}", bspx.errors),
+        format!("{:?}", parsed.bspx.unwrap().errors)
+    );
+
+    let data = fixture::map();
+    let options = ParseOptions {
+        max_total_bytes: 4 * data.len() as u64,
+        ..Default::default()
+    };
+    let data = with_repeated_bspx(data, 1000);
+    let result = block_on(Bsp::parse_async_with(
+        &mut AsyncCursor::new(&data),
+        &options,
+    ));
+    assert!(matches!(
+        result,
+        Err(BspParseError::LimitExceeded(
+            ParseLimitError::TotalBytes { .. }
+        ))
+    ));
+}
+
+#[test]
+fn test_parse_async_element_budget() {
+    let mut options = ParseOptions::default();
+    options.max_elements[LUMP_LEAVES.0] = 2;
+    let data = fixture::map();
+    let mut read = AsyncCursor::new(&data);
+    let result = block_on(Bsp::parse_async_with(&mut read, &options));
+    assert!(matches!(
+        result,
+        Err(BspParseError::LimitExceeded(
+            ParseLimitError::Elements { .. }
+        ))
+    ));
+    assert_eq!(read.position(), std::mem::size_of::<BspHeader>() as u64);
+}
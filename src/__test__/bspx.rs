@@ -0,0 +1,132 @@
+use std::io::Cursor;
+
+use bytemuck::{bytes_of, cast_slice};
+
+use crate::{
+    bsp::Bsp,
+    lumps::bspx::{BspxBrushHeader, BspxBrushModelHeader, BspxBrushPlane},
+    math::Vector3D,
+};
+
+use super::fixture;
+
+pub(super) fn with_bspx(mut data: Vec<u8>, lumps: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    while !data.len().is_multiple_of(4) {
+        data.push(0);
+    }
+    let mut directory = b"BSPX".to_vec();
+    directory.extend_from_slice(bytes_of(&(lumps.len() as i32)));
+    let mut offset = data.len() + 8 + lumps.len() * 32;
+    let mut body = vec![];
+    for (name, lump) in lumps {
+        let mut sz_name = [0u8; 24];
+        sz_name[..name.len()].copy_from_slice(name.as_bytes());
+        directory.extend_from_slice(&sz_name);
+        directory.extend_from_slice(bytes_of(&(offset as i32)));
+        directory.extend_from_slice(bytes_of(&(lump.len() as i32)));
+        body.extend_from_slice(lump);
+        while !body.len().is_multiple_of(4) {
+            body.push(0);
+        }
+        offset = data.len() + 8 + lumps.len() * 32 + body.len();
+    }
+    data.extend(directory);
+    data.extend(body);
+    data
+}
+
+fn brush_list() -> Vec<u8> {
+    let mut out = bytes_of(&BspxBrushModelHeader {
+        n_version: 1,
+        i_model: 0,
+        n_brushes: 1,
+        n_planes: 1,
+    })
+    .to_vec();
+    out.extend_from_slice(bytes_of(&BspxBrushHeader {
+        n_mins: [-8.0; 3],
+        n_maxs: [8.0; 3],
+        n_contents: -2,
+        n_planes: 1,
+    }));
+    out.extend_from_slice(bytes_of(&BspxBrushPlane {
+        v_normal: Vector3D {
+            x: 0.6,
+            y: 0.8,
+            z: 0.0,
+        },
+        f_dist: 4.0,
+    }));
+    out
+}
+
+fn face_normals() -> Vec<u8> {
+    let mut out = bytes_of(&1u32).to_vec();
+    out.extend_from_slice(cast_slice(&[1.0f32, 0.0, 0.0]));
+    out.extend_from_slice(cast_slice(&[0u32; 12]));
+    out
+}
+
+#[test]
+fn test_without_bspx() {
+    let bsp = Bsp::parse(&mut Cursor::new(fixture::map())).unwrap();
+    assert!(bsp.bspx.is_none());
+}
+
+#[test]
+fn test_bspx_lumps() {
+    let data = with_bspx(
+        fixture::map(),
+        &[
+            ("RGBLIGHTING", (0..12).rev().collect()),
+            ("LMSHIFT", vec![4]),
+            ("LMOFFSET", 0i32.to_le_bytes().to_vec()),
+            ("FACENORMALS", face_normals()),
+            ("BRUSHLIST", brush_list()),
+            ("CUSTOM", vec![1, 2, 3]),
+        ],
+    );
+    let bsp = Bsp::parse(&mut Cursor::new(data)).unwrap();
+    let bspx = bsp.bspx.unwrap();
+    assert_eq!(bspx.lumps.len(), 6);
+    assert_eq!(bspx.get("custom"), Some(&[1u8, 2, 3][..]));
+    assert!(bspx.errors.is_empty());
+
+    let lighting = bspx.rgb_lighting.as_ref().unwrap();
+    assert_eq!(lighting.0.len(), 4);
+    assert_eq!(lighting[0].0, 11);
+    assert_eq!(bspx.lm_shift.as_ref().unwrap().0, vec![4]);
+    assert_eq!(bspx.lm_offset.as_ref().unwrap().0, vec![0]);
+
+    let normals = bspx.face_normals.as_ref().unwrap();
+    assert_eq!(normals.normals.len(), 1);
+    assert_eq!(normals.vertices.len(), 4);
+
+    let brushes = bspx.brush_list.as_ref().unwrap();
+    assert_eq!(brushes.0.len(), 1);
+    assert_eq!(brushes.0[0].brushes[0].header.n_contents, -2);
+    assert_eq!(brushes.0[0].brushes[0].planes[0].f_dist, 4.0);
+}
+
+#[test]
+fn test_bad_bspx_lumps() {
+    let mut data = with_bspx(
+        fixture::map(),
+        &[
+            ("LMSHIFT", vec![4]),
+            ("RGBLIGHTING", vec![1, 2]),
+            ("CUSTOM", vec![1, 2, 3]),
+        ],
+    );
+    // Points the last entry past the end of the file.
+    let len = data.len() as i32;
+    let entry = data.len() - 4 - 32 - 8;
+    data[entry + 24..entry + 28].copy_from_slice(bytes_of(&len));
+    let bsp = Bsp::parse(&mut Cursor::new(data)).unwrap();
+    let bspx = bsp.bspx.unwrap();
+    assert_eq!(bspx.lm_shift.unwrap().0, vec![4]);
+    assert!(bspx.rgb_lighting.is_none());
+    assert_eq!(bspx.lumps.len(), 2);
+    let errors: Vec<_> = bspx.errors.iter().map(|err| err.name.as_str()).collect();
+    assert_eq!(errors, vec!["CUSTOM", "RGBLIGHTING"]);
+}
@@ -0,0 +1,224 @@
+use std::io::Cursor;
+
+use crate::{
+    bsp::Bsp,
+    header::LUMP_ENTITIES,
+    lumps::entities::{BspEntitiesLump, BspEntity, BspEntityValueError},
+    parsing::{
+        decoding::BspParseError,
+        entities::{decode_entities, decode_entities_recovering, BspEntityErrorKind},
+        options::ParseOptions,
+    },
+};
+
+use super::fixture;
+
+#[test]
+fn test_empty_values_and_comments() {
+    let text =
+        b"// generated\n{\n\"classname\" \"worldspawn\" // the world\n\"target\" \"\"\n}\n\0";
+    let entities = decode_entities(text).unwrap();
+    assert_eq!(entities.0.len(), 1);
+    let pairs = &entities[0].0;
+    assert_eq!(pairs.len(), 2);
+    assert_eq!(pairs[1].0, "target");
+    assert!(pairs[1].1.is_empty());
+}
+
+#[test]
+fn test_empty_lump() {
+    assert!(decode_entities(b"").unwrap().0.is_empty());
+    assert!(decode_entities(b"\0").unwrap().0.is_empty());
+    assert_eq!(decode_entities(b"{\"a\" \"b\"}").unwrap().0.len(), 1);
+}
+
+#[test]
+fn test_unquoted_tokens() {
+    let entities = decode_entities(b"{classname light _light \"255 255 255 200\"}").unwrap();
+    let pairs = &entities[0].0;
+    assert_eq!(pairs[0], ("classname".into(), "light".into()));
+    assert_eq!(pairs[1].1, "255 255 255 200");
+}
+
+#[test]
+fn test_cp1252_values() {
+    let text = b"{\n\"message\" \"Caf\xe9 \x80\"\n}\n\0";
+    let entities = decode_entities(text).unwrap();
+    let value = &entities[0].0[0].1;
+    assert_eq!(value.as_bytes(), b"Caf\xe9 \x80");
+    assert_eq!(value.to_str(), "Café €");
+
+    let mut lumps = fixture::lumps();
+    lumps[LUMP_ENTITIES.0] = text.to_vec();
+    let data = fixture::assemble(30, &lumps);
+    let bsp = Bsp::parse(&mut Cursor::new(&data)).unwrap();
+    assert_eq!(fixture::written(&bsp), data);
+}
+
+#[test]
+fn test_entity_errors() {
+    let Err(BspParseError::EntityLumpParseError(err)) =
+        decode_entities(b"{\n\"classname\" \"worldspawn\"\n\"wad\" }\n")
+    else {
+        panic!("expected an entity error");
+    };
+    assert_eq!(err.kind, BspEntityErrorKind::MissingValue);
+    assert_eq!((err.line, err.column, err.offset), (3, 7, 33));
+    assert_eq!(
+        err.to_string(),
+        "closing brace without data at line 3, column 7 (byte 33)"
+    );
+
+    let Err(BspParseError::EntityLumpParseError(err)) = decode_entities(b"{ \"a\" \"b\" } x")
+    else {
+        panic!("expected an entity error");
+    };
+    assert_eq!(err.kind, BspEntityErrorKind::ExpectedOpenBrace);
+
+    let Err(BspParseError::EntityLumpParseError(err)) = decode_entities(b"{ \"a\" \"b\"") else {
+        panic!("expected an entity error");
+    };
+    assert_eq!(err.kind, BspEntityErrorKind::UnexpectedEnd);
+}
+
+#[test]
+fn test_recovering() {
+    let text = b"{\n\"classname\" \"worldspawn\"\n}\n{\n\"classname\" \"light\"\n\"target\" }\n{\n\"classname\" \"info_player_start\"\n}\nbroken\n{\n\"classname\" \"info_null\"\n}\n{\n\"classname\" \"func_wall\"\n{\n\"classname\" \"info_target\"\n}\n\0";
+    assert!(decode_entities(text).is_err());
+
+    let (entities, errors) = decode_entities_recovering(text);
+    let classnames: Vec<_> = entities
+        .0
+        .iter()
+        .map(|entity| entity.0[0].1.to_string())
+        .collect();
+    assert_eq!(
+        classnames,
+        vec![
+            "worldspawn",
+            "info_player_start",
+            "info_null",
+            "info_target"
+        ]
+    );
+    let kinds: Vec<_> = errors.iter().map(|err| (err.kind, err.line)).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (BspEntityErrorKind::MissingValue, 6),
+            (BspEntityErrorKind::ExpectedOpenBrace, 10),
+            (BspEntityErrorKind::UnexpectedOpenBrace, 16),
+        ]
+    );
+
+    let mut lumps = fixture::lumps();
+    lumps[LUMP_ENTITIES.0] = text.to_vec();
+    let data = fixture::assemble(30, &lumps);
+    assert!(Bsp::parse(&mut Cursor::new(&data)).is_err());
+    let options = ParseOptions {
+        recover_entities: true,
+        ..ParseOptions::unlimited()
+    };
+    let bsp = Bsp::parse_with(&mut Cursor::new(&data), &options).unwrap();
+    assert_eq!(bsp.entities.0.len(), 4);
+    assert_eq!(bsp.entity_diagnostics, errors);
+}
+
+#[test]
+fn test_entity_accessors() {
+    let text = b"{\n\"classname\" \"func_door\"\n\"targetname\" \"door1\"\n\"target\" \"a\"\n\"target\" \"b\"\n\"origin\" \" 16 -8.5 32 \"\n\"spawnflags\" \"257\"\n\"rendercolor\" \"255 128 0\"\n\"angle\" \"-1\"\n}\n";
+    let entities = decode_entities(text).unwrap();
+    let door = &entities[0];
+    assert_eq!(door.classname().unwrap(), "func_door");
+    assert_eq!(door.targetname().unwrap(), "door1");
+    assert_eq!(door.get("target").unwrap(), "b");
+    assert_eq!(door.get_all("target").collect::<Vec<_>>(), ["a", "b"]);
+    assert!(door.get("model").is_none());
+    let origin = door.origin().unwrap();
+    assert_eq!((origin.x, origin.y, origin.z), (16.0, -8.5, 32.0));
+    assert_eq!(door.spawnflags().unwrap(), 257);
+    assert_eq!(door.rendercolor().unwrap(), [255, 128, 0]);
+    assert_eq!(door.angles().unwrap().x, -90.0);
+    let keys: Vec<_> = door.iter().map(|(key, _)| key.to_string()).collect();
+    assert_eq!(keys[..4], ["classname", "targetname", "target", "target"]);
+}
+
+#[test]
+fn test_entity_angles() {
+    let angles = |text: &str| {
+        let entities = decode_entities(text.as_bytes()).unwrap();
+        let angles = entities[0].angles().unwrap();
+        (angles.x, angles.y, angles.z)
+    };
+    assert_eq!(angles("{ }"), (0.0, 0.0, 0.0));
+    assert_eq!(angles("{ \"angle\" \"90\" }"), (0.0, 90.0, 0.0));
+    assert_eq!(angles("{ \"angle\" \"-2\" }"), (90.0, 0.0, 0.0));
+    assert_eq!(angles("{ \"angle\" \"-90\" }"), (90.0, 0.0, 0.0));
+    assert_eq!(angles("{ \"angle\" \"-1.5\" }"), (-90.0, 0.0, 0.0));
+    assert_eq!(angles("{ \"angles\" \"10 20 30\" }"), (10.0, 20.0, 30.0));
+    assert_eq!(
+        angles("{ \"angles\" \"10 20 30\" \"angle\" \"45\" }"),
+        (10.0, 45.0, 30.0)
+    );
+    assert_eq!(
+        angles("{ \"angle\" \"45\" \"angles\" \"10 20 30\" }"),
+        (10.0, 20.0, 30.0)
+    );
+    assert_eq!(
+        angles("{ \"angles\" \"10 20 30\" \"angle\" \"-2\" }"),
+        (90.0, 0.0, 0.0)
+    );
+}
+
+#[test]
+fn test_entity_value_errors() {
+    let text = b"{ \"origin\" \"1 2\" \"spawnflags\" \"-1\" \"rendercolor\" \"256 0 0\" }";
+    let entities = decode_entities(text).unwrap();
+    let entity = &entities[0];
+    let err = entity.origin().unwrap_err();
+    assert_eq!(
+        err,
+        BspEntityValueError {
+            key: "origin".to_owned(),
+            value: "1 2".to_owned(),
+            expected: "3 numbers",
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "bad value \"1 2\" for \"origin\", expected 3 numbers"
+    );
+    assert_eq!(entity.spawnflags().unwrap_err().key, "spawnflags");
+    assert_eq!(entity.rendercolor().unwrap_err().key, "rendercolor");
+    let bsp = Bsp::parse(&mut Cursor::new(fixture::map())).unwrap();
+    assert_eq!(bsp.entities[0].origin().unwrap().x, 0.0);
+    assert_eq!(bsp.entities[1].origin().unwrap().x, -32.0);
+}
+
+#[test]
+fn test_edit_entities() {
+    let mut entities = BspEntitiesLump::default();
+    entities.push(BspEntity::from_iter([("classname", "worldspawn")]));
+    entities.insert(
+        1,
+        BspEntity::from_iter([("classname", "light"), ("target", "a"), ("target", "b")]),
+    );
+    let light = &mut entities[1];
+    light.set("target", "c");
+    light.set("_light", "255 255 255 200");
+    light.push("target", "d");
+    assert_eq!(light.get_all("target").collect::<Vec<_>>(), ["a", "c", "d"]);
+    assert_eq!(light.remove("target").unwrap(), "d");
+    assert!(light.remove("target").is_none());
+    assert_eq!(light.0.len(), 2);
+
+    let text = entities.to_bytes().unwrap();
+    assert_eq!(
+        text,
+        b"{\n\"classname\" \"worldspawn\"\n}\n{\n\"classname\" \"light\"\n\"_light\" \"255 255 255 200\"\n}\n\0"
+    );
+    assert_eq!(decode_entities(&text).unwrap().len(), 2);
+    entities.retain(|entity| entity.classname().as_deref() != Some("light"));
+    assert_eq!(entities.remove(0).classname().unwrap(), "worldspawn");
+    assert!(entities.is_empty());
+}
@@ -0,0 +1,92 @@
+use std::{error::Error, io::Cursor};
+
+use crate::{
+    bsp::Bsp,
+    header::{BspLumpPointer, LUMP_ENTITIES, LUMP_MODELS, LUMP_PLANES, LUMP_UNKNOWN},
+    lumps::vertices::BspVerticesLump,
+    parsing::decoding::{BspLumpError, BspLumpErrorKind, BspParseError, LumpExtractor},
+    view::BspView,
+};
+
+use super::fixture;
+
+fn lump_error(data: Vec<u8>) -> BspLumpError {
+    match Bsp::parse(&mut Cursor::new(data)) {
+        Err(BspParseError::Lump(err)) => err,
+        other => panic!("expected a lump error, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_partial_element() {
+    let mut lumps = fixture::lumps();
+    lumps[LUMP_PLANES.0].push(0);
+    let err = lump_error(fixture::assemble(30, &lumps));
+    assert_eq!(err.lump, LUMP_PLANES);
+    assert_eq!(err.element_size, 20);
+    assert_eq!(err.ptr.n_length, 21);
+    assert!(matches!(err.kind, BspLumpErrorKind::PartialElement));
+    assert!(err.to_string().starts_with("planes lump (offset "));
+}
+
+#[test]
+fn test_truncated_file() {
+    let mut data = fixture::map();
+    data.truncate(data.len() - 8);
+    let file_len = data.len() as u64;
+    let err = lump_error(data);
+    assert_eq!(err.lump, LUMP_MODELS);
+    assert_eq!(err.file_len, file_len);
+    assert!(matches!(err.kind, BspLumpErrorKind::PastEndOfFile));
+    assert!(err.to_string().contains("8 bytes past the end of the file"));
+}
+
+#[test]
+fn test_decoding_error_source() {
+    let mut lumps = fixture::lumps();
+    lumps[LUMP_ENTITIES.0] = b"{\n\"classname\" }\n\0".to_vec();
+    let data = fixture::assemble(30, &lumps);
+    let err = Bsp::parse(&mut Cursor::new(data)).unwrap_err();
+    let BspParseError::Lump(lump) = &err else {
+        panic!("expected a lump error, got {err:?}");
+    };
+    assert_eq!(lump.lump, LUMP_ENTITIES);
+    assert!(matches!(lump.kind, BspLumpErrorKind::Decoding(_)));
+    assert!(err.source().is_some());
+}
+
+#[test]
+fn test_view_lump_error() {
+    let mut data = fixture::map();
+    let offset = 4 + LUMP_PLANES.0 * 8 + 4;
+    data[offset..offset + 4].copy_from_slice(&(-1i32).to_le_bytes());
+    let Err(BspParseError::Lump(err)) = BspView::new(&data) else {
+        panic!("expected a lump error");
+    };
+    assert_eq!(err.lump, LUMP_PLANES);
+    assert!(matches!(err.kind, BspLumpErrorKind::NegativePointer));
+}
+
+/// An extractor that doesn't name its lump, like ones written before
+/// `LumpExtractor::LUMP` existed.
+struct UnnamedExtractor(BspLumpPointer);
+
+impl LumpExtractor<BspVerticesLump> for UnnamedExtractor {
+    fn get_pointer(&self) -> BspLumpPointer {
+        self.0
+    }
+}
+
+#[test]
+fn test_unnamed_extractor() {
+    let extractor = UnnamedExtractor(BspLumpPointer {
+        n_offset: 0,
+        n_length: 13,
+    });
+    let result: Result<BspVerticesLump, _> = extractor.extract_lump(&mut Cursor::new(vec![0; 64]));
+    let Err(BspParseError::Lump(err)) = result else {
+        panic!("expected a lump error");
+    };
+    assert_eq!(err.lump, LUMP_UNKNOWN);
+    assert!(err.to_string().starts_with("unknown lump"));
+}
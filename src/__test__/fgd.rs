@@ -0,0 +1,265 @@
+use crate::{
+    fgd::{
+        parser::{FgdErrorKind, FgdParseError},
+        validation::{FgdIssue, FgdIssueKind, FgdValue},
+        Fgd, FgdChoice, FgdClassKind, FgdFlag, FgdKeyType,
+    },
+    parsing::entities::decode_entities,
+};
+
+const FGD: &str = r#"
+// Test game data
+@mapsize(-4096, 4096)
+@include "base.fgd"
+
+@BaseClass = Targetname [ targetname(target_source) : "Name" ]
+@BaseClass = RenderFields
+[
+    rendermode(choices) : "Render Mode" : 0 =
+    [
+        0: "Normal"
+        4: "Solid"
+    ]
+    rendercolor(color255) : "FX Color (R G B)" : "0 0 0"
+]
+
+@BaseClass base(Targetname) = Light
+[
+    _light(color255) : "Brightness" : "255 255 128 200"
+    style(integer) : "Appearance" :  : "Light style"
+]
+
+@PointClass base(Light) iconsprite("sprites/light.spr") size(-8 -8 -8, 8 8 8) = light : "Invisible " +
+    "light source"
+[
+    spawnflags(flags) =
+    [
+        1 : "Initially dark" : 0
+        2 : "Other" : 1
+    ]
+]
+
+@PointClass base(Targetname) studio("models/scientist.mdl") = monster_scientist : "Scientist"
+[
+    model(studio) : "Model"
+    health(float) : "Health" : "20.5"
+]
+
+@SolidClass base(Targetname, RenderFields) = func_wall : "Wall" []
+"#;
+
+fn fgd() -> Fgd {
+    Fgd::parse(FGD).unwrap()
+}
+
+#[test]
+fn test_parse_fgd() {
+    let fgd = fgd();
+    assert_eq!(fgd.includes, ["base.fgd"]);
+    assert_eq!(fgd.classes.len(), 6);
+    let light = fgd.class("light").unwrap();
+    assert_eq!(light.kind, FgdClassKind::Point);
+    assert_eq!(light.description.as_deref(), Some("Invisible light source"));
+    assert_eq!(light.bases, ["Light"]);
+    assert_eq!(light.helper("size").unwrap().args, ["-8 -8 -8", "8 8 8"]);
+    assert_eq!(
+        light.keys[0].kind,
+        FgdKeyType::Flags(vec![
+            FgdFlag {
+                bit: 1,
+                label: "Initially dark".to_owned(),
+                default: false,
+            },
+            FgdFlag {
+                bit: 2,
+                label: "Other".to_owned(),
+                default: true,
+            },
+        ])
+    );
+    let scientist = fgd.class("monster_scientist").unwrap();
+    assert_eq!(
+        scientist.helper("studio").unwrap().args,
+        ["models/scientist.mdl"]
+    );
+    assert_eq!(scientist.keys[0].kind, FgdKeyType::Studio);
+    assert_eq!(scientist.keys[0].default, None);
+
+    let base = fgd.class("Light").unwrap();
+    assert_eq!(base.kind, FgdClassKind::Base);
+    assert_eq!(base.keys[0].kind, FgdKeyType::Color255);
+    assert_eq!(base.keys[1].default, None);
+    assert_eq!(base.keys[1].description.as_deref(), Some("Light style"));
+
+    let render = fgd.class("RenderFields").unwrap();
+    let FgdKeyType::Choices(choices) = &render.keys[0].kind else {
+        panic!("expected choices");
+    };
+    assert_eq!(
+        choices[1],
+        FgdChoice {
+            value: "4".to_owned(),
+            label: "Solid".to_owned(),
+        }
+    );
+    assert_eq!(fgd.class("func_wall").unwrap().kind, FgdClassKind::Solid);
+}
+
+#[test]
+fn test_inherited_keys() {
+    let fgd = fgd();
+    let names = |class| -> Vec<String> {
+        fgd.keys(fgd.class(class).unwrap())
+            .iter()
+            .map(|key| key.name.clone())
+            .collect()
+    };
+    assert_eq!(
+        names("light"),
+        ["targetname", "_light", "style", "spawnflags"]
+    );
+    assert_eq!(
+        names("func_wall"),
+        ["targetname", "rendermode", "rendercolor"]
+    );
+}
+
+#[test]
+fn test_fgd_errors() {
+    let err = Fgd::parse("@PointClass = foo\n[\n    bar(string) : \"Bar\" =\n]").unwrap_err();
+    assert_eq!(
+        err,
+        FgdParseError {
+            line: 4,
+            column: 1,
+            kind: FgdErrorKind::Unexpected {
+                expected: "`[`",
+                found: "]".to_owned(),
+            },
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "expected `[`, found `]` at line 4, column 1"
+    );
+    let err = Fgd::parse("@NPCClass = foo []").unwrap_err();
+    assert_eq!(
+        err.kind,
+        FgdErrorKind::UnknownClassType("NPCClass".to_owned())
+    );
+    let err = Fgd::parse("@PointClass = foo [").unwrap_err();
+    assert!(matches!(err.kind, FgdErrorKind::UnexpectedEnd { .. }));
+}
+
+#[test]
+fn test_validate_entities() {
+    let entities = decode_entities(
+        br#"
+{ "classname" "worldspawn" }
+{ "classname" "light" "origin" "0 0 0" "_light" "255 300 0" "spawnflags" "5" "style" "x" }
+{ "classname" "func_wall" "model" "*1" "rendermode" "2" "zhlt_lightflags" "1" }
+{ "classname" "monster_scientist" "targetname" "sci" "health" "10" }
+"#,
+    )
+    .unwrap();
+    let issues = fgd().validate(&entities);
+    let kinds: Vec<_> = issues
+        .iter()
+        .map(|issue| (issue.entity, &issue.kind))
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            (
+                0,
+                &FgdIssueKind::UnknownClass(Some("worldspawn".to_owned()))
+            ),
+            (
+                1,
+                &FgdIssueKind::WrongType {
+                    key: "_light".to_owned(),
+                    value: "255 300 0".to_owned(),
+                    expected: "3 integers from 0 to 255 and a brightness",
+                }
+            ),
+            (1, &FgdIssueKind::UndefinedFlags(4)),
+            (
+                1,
+                &FgdIssueKind::WrongType {
+                    key: "style".to_owned(),
+                    value: "x".to_owned(),
+                    expected: "an integer",
+                }
+            ),
+            (
+                2,
+                &FgdIssueKind::InvalidChoice {
+                    key: "rendermode".to_owned(),
+                    value: "2".to_owned(),
+                }
+            ),
+            (2, &FgdIssueKind::UnknownKey("zhlt_lightflags".to_owned())),
+        ]
+    );
+    assert_eq!(
+        issues[2],
+        FgdIssue {
+            entity: 1,
+            kind: FgdIssueKind::UndefinedFlags(4),
+        }
+    );
+    assert_eq!(
+        issues[2].to_string(),
+        "entity 1: spawnflags bits 0x4 are not defined"
+    );
+}
+
+#[test]
+fn test_decode_entity() {
+    let entities = decode_entities(
+        br#"
+{ "classname" "func_wall" "rendermode" "4.0" "rendercolor" "255 0 16" }
+{ "classname" "light" "targetname" "lamp" }
+"#,
+    )
+    .unwrap();
+    let fgd = fgd();
+    let wall = fgd.decode(&entities[0]).unwrap();
+    assert_eq!(wall.classname, "func_wall");
+    assert!(wall.get("targetname").is_none());
+    assert_eq!(
+        wall.get("rendermode"),
+        Some(&FgdValue::Choice {
+            value: "4".to_owned(),
+            label: "Solid".to_owned(),
+        })
+    );
+    assert_eq!(
+        wall.get("rendercolor"),
+        Some(&FgdValue::Color255 {
+            rgb: [255, 0, 16],
+            brightness: None,
+        })
+    );
+    let light = fgd.decode(&entities[1]).unwrap();
+    assert_eq!(
+        light.values,
+        [
+            ("targetname".to_owned(), FgdValue::String("lamp".to_owned())),
+            (
+                "_light".to_owned(),
+                FgdValue::Color255 {
+                    rgb: [255, 255, 128],
+                    brightness: Some(200.0),
+                }
+            ),
+            (
+                "spawnflags".to_owned(),
+                FgdValue::Flags {
+                    bits: 2,
+                    labels: vec!["Other".to_owned()],
+                }
+            ),
+        ]
+    );
+}
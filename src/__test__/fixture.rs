@@ -0,0 +1,235 @@
+//! A tiny, hand-built BSP30 map used by the tests that must not depend on the
+//! map files under `maps/`.
+//!
+//! The world is split by the plane `x = 0` into two empty leaves. A single
+//! quad lies on the splitting plane, and the front leaf can see the back one
+//! but not the other way around.
+
+use std::io::Cursor;
+
+use bytemuck::{bytes_of, cast_slice};
+
+use crate::{
+    bsp::Bsp,
+    header::{BspHeader, BspLumpPointer, HEADER_LUMPS},
+    lumps::{
+        clip_nodes::BspClipNode,
+        faces::BspFace,
+        leaves::{BspLeaf, BspLeafContent, CONTENTS_EMPTY, CONTENTS_SOLID},
+        models::BspModel,
+        nodes::BspNode,
+        planes::{BspPlane, X},
+        surfaces::{BspEdge, BspMarkSurface, BspSurfEdge},
+        tex_info::TexInfo,
+        vertices::BspVertex,
+    },
+    math::Vector3D,
+};
+
+pub const ENTITIES: &str = "{\n\"classname\" \"worldspawn\"\n\"wad\" \"\\half-life\\valve\\fixture.wad\"\n}\n{\n\"classname\" \"info_player_start\"\n\"origin\" \"-32 0 64\"\n}\n";
+
+fn vec3(x: f32, y: f32, z: f32) -> Vector3D {
+    Vector3D { x, y, z }
+}
+
+fn leaf(contents: BspLeafContent, vis_offset: i32, first: u16, count: u16) -> BspLeaf {
+    BspLeaf {
+        n_contents: contents,
+        n_vis_offset: vis_offset,
+        n_mins: [-64, -64, -64],
+        n_maxs: [64, 64, 64],
+        i_fist_mark_surface: first,
+        n_mark_surfaces: count,
+        n_ambient_levels: [0; 4],
+    }
+}
+
+/// Builds an embedded 16x16 miptex followed by its palette.
+pub fn embedded_texture(name: &str) -> Vec<u8> {
+    let mut sz_name = [0u8; 16];
+    sz_name[..name.len()].copy_from_slice(name.as_bytes());
+    let mut out = sz_name.to_vec();
+    let sizes = [256usize, 64, 16, 4];
+    out.extend_from_slice(bytes_of(&16i32));
+    out.extend_from_slice(bytes_of(&16i32));
+    let mut offset = 40i32;
+    for size in sizes {
+        out.extend_from_slice(bytes_of(&offset));
+        offset += size as i32;
+    }
+    for size in sizes {
+        out.extend((0..size).map(|i| i as u8));
+    }
+    out.extend_from_slice(bytes_of(&256u16));
+    for i in 0..=255u8 {
+        out.extend_from_slice(&[i, 255 - i, i / 2]);
+    }
+    out.extend_from_slice(&[0, 0]);
+    out
+}
+
+/// Builds a miptex header whose data lives in an external WAD.
+fn external_texture(name: &str) -> Vec<u8> {
+    let mut sz_name = [0u8; 16];
+    sz_name[..name.len()].copy_from_slice(name.as_bytes());
+    let mut out = sz_name.to_vec();
+    out.extend_from_slice(bytes_of(&64i32));
+    out.extend_from_slice(bytes_of(&32i32));
+    out.extend_from_slice(&[0u8; 16]);
+    out
+}
+
+pub fn lumps() -> [Vec<u8>; HEADER_LUMPS] {
+    let mut entities = ENTITIES.as_bytes().to_vec();
+    entities.push(0);
+
+    let planes = [BspPlane {
+        v_normal: vec3(1.0, 0.0, 0.0),
+        f_dist: 0.0,
+        n_type: X,
+    }];
+
+    let embedded = embedded_texture("{fence");
+    let external = external_texture("crate01");
+    let mut textures = vec![];
+    textures.extend_from_slice(bytes_of(&2i32));
+    textures.extend_from_slice(bytes_of(&12i32));
+    textures.extend_from_slice(bytes_of(&(12 + embedded.len() as i32)));
+    textures.extend(embedded);
+    textures.extend(external);
+
+    let vertices = [
+        BspVertex(vec3(0.0, -64.0, -64.0)),
+        BspVertex(vec3(0.0, 64.0, -64.0)),
+        BspVertex(vec3(0.0, 64.0, 64.0)),
+        BspVertex(vec3(0.0, -64.0, 64.0)),
+    ];
+
+    let visibility: Vec<u8> = vec![0b11, 0b10];
+
+    let nodes = [BspNode {
+        plane_index: 0,
+        children_indices: [-2, -3],
+        n_mins: [-64, -64, -64],
+        n_maxs: [64, 64, 64],
+        first_face: 0,
+        n_faces: 1,
+    }];
+
+    let tex_info = [TexInfo {
+        texture_s: bytemuck::cast([1.0f32, 0.0, 0.0, 0.0]),
+        texture_t: bytemuck::cast([0.0f32, 1.0, 0.0, 0.0]),
+        miptex_index: 0,
+        texture_flags: 0,
+    }];
+
+    let faces = [BspFace {
+        i_plane: 0,
+        n_plane_side: 0,
+        i_first_edge: 0,
+        n_edges: 4,
+        i_texture_info: 0,
+        n_styles: [0, 255, 255, 255],
+        n_lightmap_offset: 0,
+    }];
+
+    let lighting: Vec<u8> = (0..12).collect();
+
+    let clip_nodes = [BspClipNode {
+        i_plane: 0,
+        i_children: [CONTENTS_EMPTY.0 as i16, CONTENTS_SOLID.0 as i16],
+    }];
+
+    let leaves = [
+        leaf(CONTENTS_SOLID, -1, 0, 0),
+        leaf(CONTENTS_EMPTY, 0, 0, 1),
+        leaf(CONTENTS_EMPTY, 1, 0, 0),
+    ];
+
+    let mark_surfaces = [BspMarkSurface(0)];
+
+    let edges = [
+        BspEdge { i_vertex: [0, 0] },
+        BspEdge { i_vertex: [0, 1] },
+        BspEdge { i_vertex: [1, 2] },
+        BspEdge { i_vertex: [2, 3] },
+        BspEdge { i_vertex: [0, 3] },
+    ];
+
+    let surf_edges = [
+        BspSurfEdge(1),
+        BspSurfEdge(2),
+        BspSurfEdge(3),
+        BspSurfEdge(-4),
+    ];
+
+    let models = [BspModel {
+        n_mins: [-64.0; 3],
+        n_maxs: [64.0; 3],
+        v_origin: vec3(0.0, 0.0, 0.0),
+        i_head_nodes: [0, 0, 0, 0],
+        n_vis_leafs: 2,
+        i_first_face: 0,
+        n_faces: 1,
+    }];
+
+    [
+        entities,
+        cast_slice(&planes).to_vec(),
+        textures,
+        cast_slice(&vertices).to_vec(),
+        visibility,
+        cast_slice(&nodes).to_vec(),
+        cast_slice(&tex_info).to_vec(),
+        cast_slice(&faces).to_vec(),
+        lighting,
+        cast_slice(&clip_nodes).to_vec(),
+        cast_slice(&leaves).to_vec(),
+        cast_slice(&mark_surfaces).to_vec(),
+        cast_slice(&edges).to_vec(),
+        cast_slice(&surf_edges).to_vec(),
+        cast_slice(&models).to_vec(),
+    ]
+}
+
+/// Lays the given lumps out after the header in index order, padding each one
+/// to a 4 byte boundary.
+pub fn assemble(version: i32, lumps: &[Vec<u8>; HEADER_LUMPS]) -> Vec<u8> {
+    let order: Vec<usize> = (0..HEADER_LUMPS).collect();
+    assemble_in(version, lumps, &order)
+}
+
+/// Like `assemble`, but lays the lumps out in the given order of indices.
+pub fn assemble_in(version: i32, lumps: &[Vec<u8>; HEADER_LUMPS], order: &[usize]) -> Vec<u8> {
+    let mut header = BspHeader {
+        n_version: version,
+        lump: [BspLumpPointer {
+            n_offset: 0,
+            n_length: 0,
+        }; HEADER_LUMPS],
+    };
+    let mut body = vec![];
+    let base = std::mem::size_of::<BspHeader>();
+    for &i in order {
+        header.lump[i].n_offset = (base + body.len()) as i32;
+        header.lump[i].n_length = lumps[i].len() as i32;
+        body.extend_from_slice(&lumps[i]);
+        while !body.len().is_multiple_of(4) {
+            body.push(0);
+        }
+    }
+    let mut out = bytes_of(&header).to_vec();
+    out.extend(body);
+    out
+}
+
+pub fn map() -> Vec<u8> {
+    assemble(30, &lumps())
+}
+
+/// The bytes `Bsp::write` gives for `bsp`.
+pub fn written(bsp: &Bsp) -> Vec<u8> {
+    let mut out = Cursor::new(vec![]);
+    bsp.write(&mut out).unwrap();
+    out.into_inner()
+}
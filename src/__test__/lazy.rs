@@ -0,0 +1,57 @@
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+use crate::{bsp::Bsp, lazy::LazyBsp};
+
+use super::fixture;
+
+/// Counts the bytes read through it.
+struct CountingReader {
+    inner: Cursor<Vec<u8>>,
+    read: usize,
+}
+
+impl Read for CountingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n;
+        Ok(n)
+    }
+}
+
+impl Seek for CountingReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[test]
+fn test_lazy_reads_on_demand() {
+    let bsp = LazyBsp::open(CountingReader {
+        inner: Cursor::new(fixture::map()),
+        read: 0,
+    })
+    .unwrap();
+    let after_header = bsp.into_inner().read;
+
+    let bsp = LazyBsp::open(CountingReader {
+        inner: Cursor::new(fixture::map()),
+        read: 0,
+    })
+    .unwrap();
+    assert_eq!(bsp.entities().unwrap().0.len(), 2);
+    assert_eq!(bsp.entities().unwrap().0.len(), 2);
+    let entity_len = bsp.header.lump[0].n_length as usize;
+    assert_eq!(bsp.into_inner().read, after_header + entity_len);
+}
+
+#[test]
+fn test_lazy_matches_parse() {
+    let data = fixture::map();
+    let lazy = LazyBsp::open(Cursor::new(data.clone())).unwrap();
+    let bsp = Bsp::parse(&mut Cursor::new(data)).unwrap();
+    assert_eq!(lazy.models().unwrap().0.len(), bsp.models.0.len());
+    assert_eq!(lazy.faces().unwrap()[0].n_edges, bsp.faces[0].n_edges);
+    assert_eq!(lazy.textures().unwrap()[1].name(), "crate01");
+    assert_eq!(lazy.vis().unwrap().0, bsp.vis.0);
+    assert!(lazy.bspx().unwrap().is_none());
+}
@@ -0,0 +1,59 @@
+use std::io::Cursor;
+
+use crate::{bsp::Bsp, limits::LimitsProfile};
+
+use super::fixture;
+
+#[test]
+fn test_limits_report() {
+    let bsp = Bsp::parse(&mut Cursor::new(fixture::map())).unwrap();
+    let report = bsp.limits_report(&LimitsProfile::GOLDSRC);
+    assert_eq!(report.usages.len(), 17);
+    assert_eq!(report.exceeded().count(), 0);
+
+    let leafs = report.get("MAX_MAP_LEAFS").unwrap();
+    assert_eq!((leafs.used, leafs.max), (3, 8192));
+    let entstring = report.get("MAX_MAP_ENTSTRING").unwrap();
+    assert_eq!(entstring.used, fixture::ENTITIES.len() + 1);
+    assert_eq!(report.get("MAX_MAP_LIGHTING").unwrap().used, 12);
+}
+
+#[test]
+fn test_limits_exceeded() {
+    let bsp = Bsp::parse(&mut Cursor::new(fixture::map())).unwrap();
+    let profile = LimitsProfile {
+        clipnodes: 2,
+        faces: 1,
+        models: 0,
+        ..LimitsProfile::SVEN_COOP
+    };
+    let report = bsp.limits_report(&profile);
+    assert_eq!(report.get("MAX_MAP_CLIPNODES").unwrap().utilisation(), 50.0);
+    assert_eq!(report.get("MAX_MAP_FACES").unwrap().utilisation(), 100.0);
+    let exceeded: Vec<_> = report.exceeded().map(|usage| usage.name).collect();
+    assert_eq!(exceeded, vec!["MAX_MAP_MODELS"]);
+    assert_eq!(report.highest().unwrap().name, "MAX_MAP_MODELS");
+    assert!(report.to_string().starts_with("Sven Co-op limits:\n"));
+}
+
+#[test]
+fn test_profiles_fit_bsp30() {
+    for profile in [
+        LimitsProfile::GOLDSRC,
+        LimitsProfile::SVEN_COOP,
+        LimitsProfile::XASH3D_EXTENDED,
+    ] {
+        let children = i16::MAX as usize;
+        assert!(profile.nodes <= children, "{}", profile.name);
+        assert!(profile.clipnodes <= children, "{}", profile.name);
+        assert!(profile.leafs <= children + 1, "{}", profile.name);
+        for max in [
+            profile.planes,
+            profile.verts,
+            profile.faces,
+            profile.marksurfaces,
+        ] {
+            assert!(max <= u16::MAX as usize, "{}", profile.name);
+        }
+    }
+}
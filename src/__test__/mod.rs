@@ -1,4 +1,25 @@
+mod analysis;
+#[cfg(feature = "async")]
+mod async_io;
+mod bspx;
+mod entities;
+mod errors;
+mod fgd;
+mod fixture;
+mod lazy;
+mod limits;
+mod options;
 mod relations;
+mod stream;
+mod textures;
+mod tree;
+mod triggers;
+mod validation;
+mod versions;
+mod view;
+mod vis;
+mod wad;
+mod writing;
 
 use rstest::*;
 use rstest_reuse::{self, *};
@@ -0,0 +1,120 @@
+use std::io::Cursor;
+
+use bytemuck::bytes_of;
+
+use crate::{
+    bsp::Bsp,
+    header::{LUMP_ENTITIES, LUMP_LEAVES},
+    parsing::{
+        decoding::BspParseError,
+        options::{ParseLimitError, ParseOptions},
+    },
+};
+
+use super::fixture;
+
+fn limit_error(data: Vec<u8>, options: &ParseOptions) -> ParseLimitError {
+    match Bsp::parse_with(&mut Cursor::new(data), options) {
+        Err(BspParseError::LimitExceeded(err)) => err,
+        other => panic!("expected a limit error, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_default_options() {
+    let options = ParseOptions::default();
+    assert_eq!((options.max_key_len, options.max_value_len), (32, 1024));
+    assert!(Bsp::parse_with(&mut Cursor::new(fixture::map()), &options).is_ok());
+}
+
+/// Appends a BSPX directory of `count` lumps, all of them the whole file.
+pub(super) fn with_repeated_bspx(mut data: Vec<u8>, count: i32) -> Vec<u8> {
+    let len = data.len() as i32;
+    data.extend_from_slice(b"BSPX");
+    data.extend_from_slice(bytes_of(&count));
+    for _ in 0..count {
+        data.extend_from_slice(&[b'A'; 24]);
+        data.extend_from_slice(bytes_of(&0i32));
+        data.extend_from_slice(bytes_of(&len));
+    }
+    data
+}
+
+#[test]
+fn test_bspx_size_limit() {
+    let data = fixture::map();
+    let options = ParseOptions {
+        max_total_bytes: 4 * data.len() as u64,
+        ..Default::default()
+    };
+    let data = with_repeated_bspx(data, 1000);
+    assert!(matches!(
+        limit_error(data.clone(), &options),
+        ParseLimitError::TotalBytes { .. }
+    ));
+    assert!(matches!(
+        Bsp::parse_stream_with(&mut Cursor::new(data), &options),
+        Err(BspParseError::LimitExceeded(
+            ParseLimitError::TotalBytes { .. }
+        ))
+    ));
+}
+
+#[test]
+fn test_size_limits() {
+    let options = ParseOptions {
+        max_total_bytes: 1024,
+        ..Default::default()
+    };
+    assert!(matches!(
+        limit_error(fixture::map(), &options),
+        ParseLimitError::TotalBytes { max: 1024, .. }
+    ));
+
+    let mut options = ParseOptions::default();
+    options.max_elements[LUMP_LEAVES.0] = 2;
+    assert_eq!(
+        limit_error(fixture::map(), &options),
+        ParseLimitError::Elements {
+            lump: LUMP_LEAVES,
+            max: 2,
+            found: 3
+        }
+    );
+}
+
+#[test]
+fn test_entity_limits() {
+    let options = ParseOptions {
+        max_entities: 1,
+        ..Default::default()
+    };
+    assert!(matches!(
+        limit_error(fixture::map(), &options),
+        ParseLimitError::Entities { max: 1, found: 2 }
+    ));
+
+    let mut lumps = fixture::lumps();
+    let long = "x".repeat(1100);
+    lumps[LUMP_ENTITIES.0] =
+        format!("{{\n\"classname\" \"worldspawn\"\n\"message\" \"{long}\"\n}}\n\0").into_bytes();
+    let err = limit_error(fixture::assemble(30, &lumps), &ParseOptions::default());
+    assert_eq!(
+        err,
+        ParseLimitError::ValueLength {
+            entity: 0,
+            key: "message".into(),
+            max: 1024,
+            found: 1100
+        }
+    );
+    assert!(Bsp::parse(&mut Cursor::new(fixture::assemble(30, &lumps))).is_ok());
+}
+
+#[test]
+fn test_bogus_length() {
+    let mut data = fixture::map();
+    let entities = 4 + LUMP_ENTITIES.0 * 8 + 4;
+    data[entities..entities + 4].copy_from_slice(&i32::MAX.to_le_bytes());
+    assert!(Bsp::parse(&mut Cursor::new(data)).is_err());
+}
@@ -1,6 +1,14 @@
-use std::fs::File;
+use std::{fs::File, io::Cursor};
 
-use crate::bsp::Bsp;
+use crate::{
+    brush_models::BrushModelError,
+    bsp::Bsp,
+    header::{LUMP_EDGES, LUMP_FACES, LUMP_MODELS, LUMP_TEXTURES},
+    lumps::entities::BspEntity,
+    relational::RelationError,
+};
+
+use super::fixture;
 
 const TEST_MAP: &'static str = "maps/crossfire.bsp";
 
@@ -17,3 +25,130 @@ fn test_face_relation_functions() {
     }
     println!("n = {}", vertices_total.len());
 }
+
+#[test]
+fn test_try_relations() {
+    let bsp = Bsp::parse(&mut Cursor::new(fixture::map())).unwrap();
+    let face = &bsp.faces[0];
+    let vertices = face.try_vertices(&bsp).unwrap();
+    assert_eq!(vertices.len(), 4);
+    for (a, b) in vertices.iter().zip(face.vertices(&bsp)) {
+        assert!(std::ptr::eq(*a, b));
+    }
+    assert_eq!(face.try_plane(&bsp).unwrap().v_normal.x, 1.0);
+    assert_eq!(face.try_miptex(&bsp).unwrap().name(), "{fence");
+    assert_eq!(bsp.try_leaf_faces(1).unwrap().len(), 1);
+    assert_eq!(bsp.try_leaf_faces(2).unwrap().len(), 0);
+    assert_eq!(bsp.models[0].try_faces(&bsp).unwrap().len(), 1);
+}
+
+#[test]
+fn test_try_relations_out_of_range() {
+    let mut bsp = Bsp::parse(&mut Cursor::new(fixture::map())).unwrap();
+    bsp.surf_edges.0[1].0 = -12;
+    bsp.tex_info.0[0].miptex_index = 2;
+    let face = &bsp.faces[0];
+    assert_eq!(
+        face.try_vertices(&bsp).unwrap_err(),
+        RelationError {
+            lump: LUMP_EDGES,
+            index: 12,
+            len: 5
+        }
+    );
+    assert_eq!(face.try_miptex(&bsp).unwrap_err().lump, LUMP_TEXTURES);
+    assert_eq!(bsp.try_face(1).unwrap_err().lump, LUMP_FACES);
+    assert_eq!(
+        bsp.try_leaf(3).unwrap_err().to_string(),
+        "index 3 is out of range of the leaves lump (3 elements)"
+    );
+}
+
+#[test]
+fn test_brush_models() {
+    let mut bsp = Bsp::parse(&mut Cursor::new(fixture::map())).unwrap();
+    let mut door = bsp.models[0];
+    door.n_mins = [-8.0, -8.0, -32.0];
+    door.n_maxs = [8.0, 8.0, 32.0];
+    bsp.models.0.push(door);
+    bsp.models.0.push(door);
+    bsp.entities.push(BspEntity::from_iter([
+        ("classname", "func_door_rotating"),
+        ("model", "*1"),
+        ("origin", "128 0 32"),
+    ]));
+    bsp.entities.push(BspEntity::from_iter([
+        ("classname", "cycler"),
+        ("model", "models/scientist.mdl"),
+    ]));
+
+    let world = bsp.entities[0].brush_model(&bsp).unwrap().unwrap();
+    assert_eq!(world.index, 0);
+    assert_eq!(world.faces(&bsp).unwrap().len(), 1);
+    assert!(bsp.entities[1].brush_model(&bsp).is_none());
+    assert!(bsp.entities[3].brush_model(&bsp).is_none());
+
+    let pairs: Vec<_> = bsp
+        .brush_models()
+        .map(|(i, model)| (i, model.unwrap()))
+        .collect();
+    assert_eq!(pairs.len(), 2);
+    let (i, door) = &pairs[1];
+    assert_eq!(*i, 2);
+    assert_eq!(
+        door.entity.classname().as_deref(),
+        Some("func_door_rotating")
+    );
+    assert_eq!(door.index, 1);
+    assert!(std::ptr::eq(door.model, &bsp.models[1]));
+    assert_eq!(door.faces(&bsp).unwrap().len(), 1);
+    let (mins, maxs) = door.world_bounds();
+    assert_eq!((mins.x, mins.y, mins.z), (120.0, -8.0, 0.0));
+    assert_eq!((maxs.x, maxs.y, maxs.z), (136.0, 8.0, 64.0));
+
+    assert_eq!(bsp.unreferenced_models(), vec![2]);
+
+    bsp.models.0[1].i_first_face = -1;
+    let door = bsp.entities[2].brush_model(&bsp).unwrap().unwrap();
+    assert!(door.faces(&bsp).is_err());
+}
+
+#[test]
+fn test_brush_model_errors() {
+    let mut bsp = Bsp::parse(&mut Cursor::new(fixture::map())).unwrap();
+    bsp.entities.push(BspEntity::from_iter([
+        ("classname", "func_wall"),
+        ("model", "*4"),
+    ]));
+    bsp.entities.push(BspEntity::from_iter([
+        ("classname", "func_door"),
+        ("model", "*one"),
+    ]));
+    bsp.entities.push(BspEntity::from_iter([
+        ("classname", "func_train"),
+        ("model", "*0"),
+        ("origin", "0 0"),
+    ]));
+    let errors: Vec<_> = bsp
+        .brush_models()
+        .filter_map(|(i, model)| Some((i, model.err()?)))
+        .collect();
+    assert_eq!(errors.len(), 3);
+    assert_eq!(
+        errors[0],
+        (
+            2,
+            BrushModelError::Relation(RelationError {
+                lump: LUMP_MODELS,
+                index: 4,
+                len: 1
+            })
+        )
+    );
+    assert_eq!(
+        errors[1].1.to_string(),
+        "bad value \"*one\" for \"model\", expected a model number such as *1"
+    );
+    assert!(matches!(&errors[2].1, BrushModelError::Value(err) if err.key == "origin"));
+    assert!(bsp.unreferenced_models().is_empty());
+}
@@ -0,0 +1,126 @@
+use std::io::{Cursor, Read};
+
+use crate::{
+    bsp::Bsp,
+    header::{BspHeader, BspLumpLayout, LUMP_ENTITIES, LUMP_LEAVES, LUMP_PLANES, LUMP_VERTICES},
+    lumps::bspx::BSPX_LMSHIFT,
+    parsing::{
+        decoding::BspParseError,
+        options::{ParseLimitError, ParseOptions},
+        stream::BackwardSeekError,
+    },
+};
+
+use super::{bspx::with_bspx, fixture};
+
+/// A reader that can't seek.
+struct Pipe(Cursor<Vec<u8>>);
+
+impl Read for Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+pub(super) fn stream(data: Vec<u8>) -> Result<Box<Bsp>, BspParseError> {
+    Bsp::parse_stream(&mut Pipe(Cursor::new(data)))
+}
+
+#[test]
+fn test_stream_matches_parse() {
+    let data = fixture::map();
+    let streamed = stream(data.clone()).unwrap();
+    let parsed = Bsp::parse(&mut Cursor::new(&data)).unwrap();
+    assert_eq!(fixture::written(&streamed), fixture::written(&parsed));
+    assert_eq!(fixture::written(&streamed), data);
+}
+
+#[test]
+fn test_stream_out_of_order_lumps() {
+    let order = [14, 3, 9, 0, 7, 5, 1, 12, 2, 10, 4, 13, 6, 11, 8];
+    let data = fixture::assemble_in(30, &fixture::lumps(), &order);
+    assert_eq!(fixture::written(&stream(data.clone()).unwrap()), data);
+
+    let mut lumps = fixture::lumps();
+    lumps.swap(LUMP_ENTITIES.0, LUMP_PLANES.0);
+    let data = fixture::assemble_in(30, &lumps, &order);
+    let bsp = stream(data.clone()).unwrap();
+    assert_eq!(bsp.layout, BspLumpLayout::BlueShift);
+    assert_eq!(bsp.entities.0.len(), 2);
+    assert_eq!(fixture::written(&bsp), data);
+}
+
+#[test]
+fn test_stream_bspx() {
+    let data = with_bspx(fixture::map(), &[(BSPX_LMSHIFT, vec![4])]);
+    let bsp = stream(data.clone()).unwrap();
+    assert_eq!(
+        bsp.bspx.as_ref().unwrap().lm_shift.as_ref().unwrap().0,
+        vec![4]
+    );
+    assert_eq!(fixture::written(&bsp), data);
+}
+
+#[test]
+fn test_stream_backward_seek() {
+    let mut data = fixture::map();
+    // Point the vertices lump at the planes, which come before it.
+    let planes = 4 + LUMP_PLANES.0 * 8;
+    let vertices = 4 + LUMP_VERTICES.0 * 8;
+    let offset: [u8; 4] = data[planes..planes + 4].try_into().unwrap();
+    data[vertices..vertices + 4].copy_from_slice(&offset);
+    data[vertices + 4..vertices + 8].copy_from_slice(&12i32.to_le_bytes());
+    assert!(Bsp::parse(&mut Cursor::new(&data)).is_ok());
+    let Err(BspParseError::BackwardSeek(err)) = stream(data) else {
+        panic!("expected a backward seek error");
+    };
+    assert_eq!(
+        err,
+        BackwardSeekError {
+            name: "vertices lump".to_owned(),
+            offset: i32::from_le_bytes(offset) as u64,
+            position: i32::from_le_bytes(offset) as u64 + 20,
+        }
+    );
+    assert!(err.to_string().contains("already at offset"));
+}
+
+#[test]
+fn test_stream_truncated() {
+    let mut data = fixture::map();
+    data.truncate(data.len() - 8);
+    assert!(matches!(stream(data), Err(BspParseError::Lump(_))));
+}
+
+#[test]
+fn test_stream_bspx_directory_budget() {
+    let mut data = fixture::map();
+    let options = ParseOptions {
+        max_total_bytes: 2 * data.len() as u64,
+        ..Default::default()
+    };
+    data.extend_from_slice(b"BSPX");
+    data.extend_from_slice(&i32::MAX.to_le_bytes());
+    let result = Bsp::parse_stream_with(&mut Pipe(Cursor::new(data)), &options);
+    assert!(matches!(
+        result,
+        Err(BspParseError::LimitExceeded(
+            ParseLimitError::TotalBytes { .. }
+        ))
+    ));
+}
+
+#[test]
+fn test_stream_element_budget() {
+    let mut options = ParseOptions::default();
+    options.max_elements[LUMP_LEAVES.0] = 2;
+    let mut read = Cursor::new(fixture::map());
+    let result = Bsp::parse_stream_with(&mut read, &options);
+    assert!(matches!(
+        result,
+        Err(BspParseError::LimitExceeded(
+            ParseLimitError::Elements { .. }
+        ))
+    ));
+    assert_eq!(read.position(), std::mem::size_of::<BspHeader>() as u64);
+}
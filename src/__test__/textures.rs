@@ -0,0 +1,68 @@
+use std::io::Cursor;
+
+use bytemuck::bytes_of;
+
+use crate::{
+    bsp::Bsp,
+    header::LUMP_TEXTURES,
+    lumps::textures::BspTextureData,
+    parsing::decoding::{BspLumpErrorKind, BspParseError},
+};
+
+use super::fixture;
+
+#[test]
+fn test_embedded_texture() {
+    let bsp = Bsp::parse(&mut Cursor::new(fixture::map())).unwrap();
+    let texture = &bsp.textures[0];
+    assert_eq!(texture.name(), "{fence");
+    let pixels = texture.pixels().unwrap();
+    let sizes: Vec<_> = pixels.mips.iter().map(Vec::len).collect();
+    assert_eq!(sizes, vec![256, 64, 16, 4]);
+    assert_eq!(pixels.palette.as_ref().unwrap().0.len(), 256);
+    assert_eq!(pixels.palette.as_ref().unwrap().0[3], [3, 252, 1]);
+
+    let rgba = texture.rgba(0).unwrap();
+    assert_eq!(rgba.len(), 16 * 16 * 4);
+    assert_eq!(&rgba[12..16], &[3, 252, 1, 255]);
+    // Index 255 is see-through on `{` textures.
+    assert_eq!(&rgba[255 * 4..], &[0, 0, 0, 0]);
+    assert_eq!(texture.rgba(3).unwrap().len(), 2 * 2 * 4);
+    assert!(texture.rgba(4).is_none());
+}
+
+#[test]
+fn test_external_texture() {
+    let bsp = Bsp::parse(&mut Cursor::new(fixture::map())).unwrap();
+    let texture = &bsp.textures[1];
+    assert_eq!(texture.name(), "crate01");
+    assert!(matches!(texture.data, BspTextureData::External));
+    assert!(texture.rgba(0).is_none());
+    assert_eq!(texture.mip_tex.mip_size(2), (16, 8));
+}
+
+#[test]
+fn test_missing_texture() {
+    let mut lumps = fixture::lumps();
+    lumps[LUMP_TEXTURES.0][4..8].copy_from_slice(bytes_of(&-1i32));
+    let bsp = Bsp::parse(&mut Cursor::new(fixture::assemble(30, &lumps))).unwrap();
+    assert!(matches!(bsp.textures[0].data, BspTextureData::Missing));
+    assert!(bsp.textures[0].rgba(0).is_none());
+    assert_eq!(bsp.textures[1].name(), "crate01");
+}
+
+#[test]
+fn test_partial_mip_offsets() {
+    let mut lumps = fixture::lumps();
+    // The third mip offset of the embedded texture, which starts at 12.
+    lumps[LUMP_TEXTURES.0][44..48].copy_from_slice(bytes_of(&0i32));
+    let err = Bsp::parse(&mut Cursor::new(fixture::assemble(30, &lumps))).unwrap_err();
+    let BspParseError::Lump(err) = err else {
+        panic!("unexpected error {err:?}");
+    };
+    assert_eq!(err.lump, LUMP_TEXTURES);
+    assert!(matches!(
+        err.kind,
+        BspLumpErrorKind::Decoding(ref err) if matches!(**err, BspParseError::PartialMipTex(_))
+    ));
+}
@@ -0,0 +1,57 @@
+use std::io::Cursor;
+
+use crate::{
+    bsp::Bsp,
+    lumps::nodes::{BspNode, BspNodeChild},
+    math::Vector3D,
+    tree::BspTreeItem,
+};
+
+use super::fixture;
+
+#[test]
+fn test_node_layout() {
+    assert_eq!(std::mem::size_of::<BspNode>(), 24);
+}
+
+#[test]
+fn test_node_child_decoding() {
+    assert_eq!(BspNodeChild::from_raw(0), BspNodeChild::Node(0));
+    assert_eq!(BspNodeChild::from_raw(12), BspNodeChild::Node(12));
+    assert_eq!(BspNodeChild::from_raw(-1), BspNodeChild::Leaf(0));
+    assert_eq!(BspNodeChild::from_raw(-3), BspNodeChild::Leaf(2));
+}
+
+#[test]
+fn test_node_tree_walk() {
+    let bsp = Bsp::parse(&mut Cursor::new(fixture::map())).unwrap();
+    let trees = bsp.node_tree();
+    assert_eq!(trees.len(), 1);
+    let items: Vec<_> = trees[0]
+        .iter()
+        .map(|item| match item {
+            BspTreeItem::Node { index, depth, .. } => (true, index, depth),
+            BspTreeItem::Leaf { index, depth, .. } => (false, index, depth),
+        })
+        .collect();
+    assert_eq!(items, vec![(true, 0, 0), (false, 1, 1), (false, 2, 1)]);
+    assert_eq!(trees[0].leaves().collect::<Vec<_>>(), vec![1, 2]);
+}
+
+#[test]
+fn test_node_tree_find_leaf() {
+    let bsp = Bsp::parse(&mut Cursor::new(fixture::map())).unwrap();
+    let world = bsp.node_tree()[0];
+    let front = Vector3D {
+        x: 16.0,
+        y: 0.0,
+        z: 0.0,
+    };
+    let back = Vector3D {
+        x: -16.0,
+        y: 0.0,
+        z: 0.0,
+    };
+    assert_eq!(world.find_leaf(&front), Some(1));
+    assert_eq!(world.find_leaf(&back), Some(2));
+}
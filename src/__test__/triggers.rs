@@ -0,0 +1,75 @@
+use crate::{
+    parsing::entities::decode_entities,
+    triggers::{TriggerEdge, TriggerEdgeKind},
+};
+
+const ENTITIES: &str = r#"
+{ "classname" "worldspawn" }
+{ "classname" "trigger_once" "target" "mm" "killtarget" "blocker" }
+{ "classname" "multi_manager" "targetname" "mm" "door" "0" "door#1" "2.5" "light1" "1" "wait" "3" }
+{ "classname" "func_door" "targetname" "door" "master" "ms" }
+{ "classname" "func_door" "targetname" "door" }
+{ "classname" "multisource" "targetname" "ms" }
+{ "classname" "func_wall" "targetname" "blocker" }
+{ "classname" "info_target" "targetname" "unused" }
+"#;
+
+#[test]
+fn test_entity_index() {
+    let entities = decode_entities(ENTITIES.as_bytes()).unwrap();
+    let index = entities.index();
+    assert_eq!(index.by_classname("func_door"), [3, 4]);
+    assert_eq!(index.by_targetname("door"), [3, 4]);
+    assert_eq!(index.by_targetname("mm"), [2]);
+    assert!(index.by_classname("func_train").is_empty());
+    assert_eq!(index.classnames().count(), 7);
+}
+
+#[test]
+fn test_trigger_graph() {
+    let entities = decode_entities(ENTITIES.as_bytes()).unwrap();
+    let graph = entities.trigger_graph();
+    let edge = |from, target: &str, kind| TriggerEdge {
+        from,
+        target: target.to_owned(),
+        kind,
+    };
+    assert_eq!(
+        graph.edges,
+        [
+            edge(1, "mm", TriggerEdgeKind::Target),
+            edge(1, "blocker", TriggerEdgeKind::KillTarget),
+            edge(2, "door", TriggerEdgeKind::MultiManager { delay: 0.0 }),
+            edge(2, "door", TriggerEdgeKind::MultiManager { delay: 2.5 }),
+            edge(2, "light1", TriggerEdgeKind::MultiManager { delay: 1.0 }),
+            edge(3, "ms", TriggerEdgeKind::Master),
+        ]
+    );
+    assert_eq!(graph.targets(&graph.edges[2]), [3, 4]);
+    assert_eq!(graph.outgoing(2).count(), 3);
+    assert_eq!(graph.incoming(4).count(), 2);
+    let undefined: Vec<_> = graph.undefined_targets().collect();
+    assert_eq!(
+        undefined,
+        [&edge(
+            2,
+            "light1",
+            TriggerEdgeKind::MultiManager { delay: 1.0 }
+        )]
+    );
+    assert_eq!(graph.unfired_targetnames(), ["ms", "unused"]);
+}
+
+#[test]
+fn test_trigger_graph_dot() {
+    let entities = decode_entities(ENTITIES.as_bytes()).unwrap();
+    let dot = entities.trigger_graph().to_dot();
+    assert!(dot.starts_with("digraph triggers {\n"));
+    assert!(dot.contains("  e2 [label=\"multi_manager\\nmm\"];\n"));
+    assert!(dot.contains("  e2 -> e4 [label=\"multi_manager 2.5s\"];\n"));
+    assert!(dot.contains("  e3 -> e5 [label=\"master\", style=dashed];\n"));
+    assert!(dot.contains("  e2 -> \"?light1\" [label=\"multi_manager 1s\"];\n"));
+    assert!(dot.contains("  \"?light1\" [label=\"light1\", shape=box, style=dashed];\n"));
+    assert!(!dot.contains("e0 "));
+    assert!(dot.ends_with("}\n"));
+}
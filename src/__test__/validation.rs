@@ -0,0 +1,62 @@
+use std::io::Cursor;
+
+use crate::{
+    bsp::Bsp,
+    header::{LUMP_EDGES, LUMP_FACES, LUMP_LEAVES, LUMP_NODES, LUMP_SURFEDGES, LUMP_VERTICES},
+    lumps::surfaces::BspSurfEdge,
+};
+
+use super::fixture;
+
+fn fixture_bsp() -> Box<Bsp> {
+    Bsp::parse(&mut Cursor::new(fixture::map())).unwrap()
+}
+
+#[test]
+fn test_valid_map() {
+    assert!(fixture_bsp().validate().is_valid());
+}
+
+#[test]
+fn test_bad_references() {
+    let mut bsp = fixture_bsp();
+    bsp.faces.0[0].n_edges = 5;
+    bsp.surf_edges.0.push(BspSurfEdge(-7));
+    bsp.edges.0[2].i_vertex[1] = 9;
+    bsp.nodes.0[0].children_indices[1] = -10;
+
+    let report = bsp.validate();
+    let issues: Vec<_> = report
+        .issues
+        .iter()
+        .map(|issue| (issue.lump, issue.index, issue.field, issue.target))
+        .collect();
+    assert_eq!(
+        issues,
+        vec![
+            (LUMP_SURFEDGES, 4, "0", LUMP_EDGES),
+            (LUMP_EDGES, 2, "i_vertex[1]", LUMP_VERTICES),
+            (LUMP_NODES, 0, "children_indices[1]", LUMP_LEAVES),
+        ]
+    );
+    assert_eq!(report.issues_in(LUMP_FACES).count(), 0);
+    assert_eq!(
+        report.issues[1].to_string(),
+        "edges[2].i_vertex[1] = 9 is out of range of the vertices lump (4 elements)"
+    );
+}
+
+#[test]
+fn test_bad_range() {
+    let mut bsp = fixture_bsp();
+    bsp.faces.0[0].i_first_edge = 2;
+    let report = bsp.validate();
+    assert_eq!(report.issues.len(), 1);
+    let issue = &report.issues[0];
+    assert_eq!((issue.lump, issue.index), (LUMP_FACES, 0));
+    assert_eq!((issue.first, issue.count, issue.target_len), (2, 4, 4));
+    assert_eq!(
+        issue.to_string(),
+        "faces[0].i_first_edge = 2..6 is out of range of the surfedges lump (4 elements)"
+    );
+}
@@ -0,0 +1,67 @@
+use std::io::Cursor;
+
+use crate::{
+    bsp::Bsp,
+    header::{BspLumpLayout, BspVersion},
+    lumps::light_map::BspLightMap,
+};
+
+use super::fixture;
+
+#[test]
+fn test_goldsrc_version() {
+    let bsp = Bsp::parse(&mut Cursor::new(fixture::map())).unwrap();
+    assert_eq!(bsp.version, BspVersion::GoldSrc30);
+}
+
+#[test]
+fn test_quake_version() {
+    let mut lumps = fixture::lumps();
+    lumps[8] = vec![10, 20, 30, 40];
+    let face_end = lumps[7].len();
+    lumps[7][face_end - 4..].copy_from_slice(&2i32.to_le_bytes());
+    let bsp = Bsp::parse(&mut Cursor::new(fixture::assemble(29, &lumps))).unwrap();
+
+    assert_eq!(bsp.version, BspVersion::Quake29);
+    let lighting: Vec<_> = bsp.light_map.0.iter().map(|s| (s.0, s.1, s.2)).collect();
+    assert_eq!(
+        lighting,
+        vec![(10, 10, 10), (20, 20, 20), (30, 30, 30), (40, 40, 40)]
+    );
+    assert_eq!(bsp.faces[0].n_lightmap_offset, 6);
+    let BspLightMap(r, g, b) = bsp.light_map[2];
+    assert_eq!((r, g, b), (30, 30, 30));
+
+    let pixels = bsp.textures[0].pixels().unwrap();
+    assert!(pixels.palette.is_none());
+    assert!(bsp.textures[0].rgba(0).is_none());
+}
+
+#[test]
+fn test_unsupported_version() {
+    let data = fixture::assemble(31, &fixture::lumps());
+    assert!(Bsp::parse(&mut Cursor::new(data)).is_err());
+}
+
+#[test]
+fn test_missing_texture() {
+    let mut lumps = fixture::lumps();
+    lumps[2][8..12].copy_from_slice(&(-1i32).to_le_bytes());
+    let bsp = Bsp::parse(&mut Cursor::new(fixture::assemble(30, &lumps))).unwrap();
+    assert_eq!(bsp.textures.0.len(), 2);
+    assert_eq!(bsp.textures[1].name(), "");
+    assert!(bsp.textures[1].pixels().is_none());
+}
+
+#[test]
+fn test_blue_shift_layout() {
+    let mut lumps = fixture::lumps();
+    lumps.swap(0, 1);
+    let bsp = Bsp::parse(&mut Cursor::new(fixture::assemble(30, &lumps))).unwrap();
+    assert_eq!(bsp.layout, BspLumpLayout::BlueShift);
+    assert_eq!(bsp.entities.0.len(), 2);
+    assert_eq!(bsp.planes.0.len(), 1);
+
+    let bsp = Bsp::parse(&mut Cursor::new(fixture::map())).unwrap();
+    assert_eq!(bsp.layout, BspLumpLayout::Standard);
+}
@@ -0,0 +1,95 @@
+use std::io::Cursor;
+
+use crate::{
+    bsp::Bsp,
+    header::{BspLumpLayout, LUMP_ENTITIES, LUMP_PLANES},
+    parsing::decoding::{BspLumpErrorKind, BspParseError},
+    view::BspView,
+};
+
+use super::fixture;
+
+#[test]
+fn test_view_matches_parse() {
+    let data = fixture::map();
+    let view = BspView::new(&data).unwrap();
+    let bsp = Bsp::parse(&mut Cursor::new(&data)).unwrap();
+
+    assert_eq!(view.planes().unwrap().len(), bsp.planes.0.len());
+    assert_eq!(view.vertices().unwrap()[2].0.z, bsp.vertices[2].0.z);
+    assert_eq!(view.faces().unwrap()[0].n_edges, bsp.faces[0].n_edges);
+    assert_eq!(view.leaves().unwrap().len(), 3);
+    assert_eq!(view.surf_edges().unwrap()[3].0, -4);
+    assert_eq!(view.models().unwrap()[0].n_vis_leafs, 2);
+    assert_eq!(view.vis().unwrap(), bsp.vis.0.as_slice());
+    assert_eq!(view.light_map().unwrap().len(), 12);
+    assert_eq!(view.entities().unwrap().0.len(), 2);
+}
+
+#[test]
+fn test_view_borrows() {
+    let data = fixture::map();
+    let view = BspView::new(&data).unwrap();
+    let planes = view.planes().unwrap();
+    let start = planes.as_ptr() as usize - data.as_ptr() as usize;
+    assert_eq!(start, view.header.lump[LUMP_PLANES.0].n_offset as usize);
+}
+
+#[test]
+fn test_view_textures() {
+    let data = fixture::map();
+    let view = BspView::new(&data).unwrap();
+    assert_eq!(view.texture_count().unwrap(), 2);
+    assert_eq!(view.mip_tex(1).unwrap().unwrap().name(), "crate01");
+    assert_eq!(view.mip_level(0, 3).unwrap().unwrap(), &[0, 1, 2, 3]);
+    assert!(view.mip_level(1, 0).unwrap().is_none());
+    let texture = view.texture(0).unwrap().unwrap();
+    assert_eq!(texture.name(), "{fence");
+    assert!(matches!(
+        view.mip_level(0, 4),
+        Err(BspParseError::IndexOutOfRange { index: 4, .. })
+    ));
+    assert!(matches!(
+        view.texture(2),
+        Err(BspParseError::IndexOutOfRange { len: 2, .. })
+    ));
+}
+
+#[test]
+fn test_view_blue_shift() {
+    let mut lumps = fixture::lumps();
+    lumps.swap(LUMP_ENTITIES.0, LUMP_PLANES.0);
+    let data = fixture::assemble(30, &lumps);
+    let view = BspView::new(&data).unwrap();
+    assert_eq!(view.layout, BspLumpLayout::BlueShift);
+    assert_eq!(view.planes().unwrap().len(), 1);
+    assert_eq!(view.entities().unwrap().0.len(), 2);
+}
+
+#[test]
+fn test_view_misaligned_lump() {
+    let mut data = fixture::map();
+    data[4 + 8] += 1;
+    let view = BspView::new(&data).unwrap();
+    assert!(view.planes().is_err());
+}
+
+#[test]
+fn test_view_misaligned_buffer() {
+    let data = fixture::map();
+    // A buffer starting one byte past a 4 byte boundary.
+    let mut words: Vec<u32> = vec![0; data.len() / 4 + 1];
+    let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut words);
+    bytes[1..=data.len()].copy_from_slice(&data);
+    let view = BspView::new(&bytes[1..=data.len()]).unwrap();
+    let err = view.planes().unwrap_err();
+    let BspParseError::Lump(err) = err else {
+        panic!("unexpected error {err:?}");
+    };
+    assert!(matches!(
+        err.kind,
+        BspLumpErrorKind::Decoding(ref err) if matches!(**err, BspParseError::DeserializationError(_))
+    ));
+    assert_eq!(view.entities().unwrap().0.len(), 2);
+    assert_eq!(view.mip_tex(1).unwrap().unwrap().name(), "crate01");
+}
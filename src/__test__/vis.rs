@@ -0,0 +1,39 @@
+use std::io::Cursor;
+
+use crate::{
+    bsp::Bsp,
+    header::LUMP_LEAVES,
+    lumps::vis::{BspVisLump, LeafSet},
+};
+
+use super::fixture;
+
+#[test]
+fn test_vis_decompress() {
+    let vis = BspVisLump(vec![0xff, 0, 2, 0x81, 0x01]);
+    assert_eq!(vis.decompress(0, 32), vec![0xff, 0, 0, 0x81]);
+    assert_eq!(vis.decompress(3, 16), vec![0x81, 0x01]);
+    assert_eq!(vis.decompress(4, 24), vec![0x01, 0, 0]);
+}
+
+#[test]
+fn test_pvs() {
+    let bsp = Bsp::parse(&mut Cursor::new(fixture::map())).unwrap();
+    let front = bsp.pvs(1).unwrap();
+    assert!(front.contains(1) && front.contains(2));
+    let back = bsp.pvs(2).unwrap();
+    assert_eq!(back.iter().collect::<Vec<_>>(), vec![2]);
+    assert_eq!(bsp.pvs(0).unwrap().iter().collect::<Vec<_>>(), vec![1, 2]);
+    assert_eq!(bsp.pvs(3).unwrap_err().lump, LUMP_LEAVES);
+}
+
+#[test]
+fn test_leaf_set_union() {
+    let a: LeafSet = [1, 70].into_iter().collect();
+    let b: LeafSet = [2, 70, 130].into_iter().collect();
+    let union = a.union(&b);
+    assert_eq!(union.iter().collect::<Vec<_>>(), vec![1, 2, 70, 130]);
+    assert_eq!(union.len(), 4);
+    assert!(!union.contains(3));
+    assert!(LeafSet::new().is_empty());
+}
@@ -0,0 +1,142 @@
+use std::{fs, io::Cursor};
+
+use bytemuck::bytes_of;
+
+use crate::{
+    bsp::Bsp,
+    wad::{resolver::WadResolver, Wad, WadLump, TYP_MIPTEX, TYP_QPIC},
+};
+
+use super::fixture;
+
+fn pic(width: usize, height: usize) -> Vec<u8> {
+    let mut out = vec![];
+    out.extend_from_slice(bytes_of(&(width as i32)));
+    out.extend_from_slice(bytes_of(&(height as i32)));
+    out.extend(std::iter::repeat_n(1, width * height));
+    out.extend_from_slice(bytes_of(&256u16));
+    out.extend(std::iter::repeat_n(9, 768));
+    out
+}
+
+fn wad(entries: &[(&str, u8, Vec<u8>)]) -> Vec<u8> {
+    let mut body = vec![];
+    let mut dir = vec![];
+    for (name, n_type, data) in entries {
+        dir.extend_from_slice(bytes_of(&(12 + body.len() as i32)));
+        dir.extend_from_slice(bytes_of(&(data.len() as i32)));
+        dir.extend_from_slice(bytes_of(&(data.len() as i32)));
+        dir.extend_from_slice(&[*n_type, 0, 0, 0]);
+        let mut sz_name = [0u8; 16];
+        sz_name[..name.len()].copy_from_slice(name.as_bytes());
+        dir.extend_from_slice(&sz_name);
+        body.extend_from_slice(data);
+    }
+    let mut out = b"WAD3".to_vec();
+    out.extend_from_slice(bytes_of(&(entries.len() as i32)));
+    out.extend_from_slice(bytes_of(&(12 + body.len() as i32)));
+    out.extend(body);
+    out.extend(dir);
+    out
+}
+
+#[test]
+fn test_wad_entries() {
+    let data = wad(&[
+        (
+            "CRATE01",
+            TYP_MIPTEX.0,
+            fixture::embedded_texture("CRATE01"),
+        ),
+        ("conchars", TYP_QPIC.0, pic(4, 2)),
+    ]);
+    let mut read = Cursor::new(data);
+    let wad = Wad::parse(&mut read).unwrap();
+    assert_eq!(wad.entries.len(), 2);
+
+    let entry = *wad.find("crate01").unwrap();
+    let WadLump::MipTex(texture) = wad.extract_entry(&mut read, &entry).unwrap() else {
+        panic!("expected a miptex");
+    };
+    assert_eq!(texture.pixels().unwrap().mips[3], vec![0, 1, 2, 3]);
+    assert_eq!(
+        &texture.rgba(0).unwrap()[..8],
+        &[0, 255, 0, 255, 1, 254, 0, 255]
+    );
+
+    let entry = *wad.find("CONCHARS").unwrap();
+    let WadLump::Pic(pic) = wad.extract_entry(&mut read, &entry).unwrap() else {
+        panic!("expected a qpic");
+    };
+    assert_eq!((pic.width, pic.height, pic.pixels.len()), (4, 2, 8));
+}
+
+#[test]
+fn test_wad_bad_magic() {
+    let mut data = wad(&[]);
+    data[3] = b'2';
+    assert!(Wad::parse(&mut Cursor::new(data)).is_err());
+}
+
+#[test]
+fn test_wad_resolver() {
+    let bsp = Bsp::parse(&mut Cursor::new(fixture::map())).unwrap();
+    assert_eq!(
+        WadResolver::wad_list(&bsp),
+        vec!["\\half-life\\valve\\fixture.wad"]
+    );
+
+    let dir = std::env::temp_dir().join(format!("bsp-lib-wad-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let data = wad(&[(
+        "CRATE01",
+        TYP_MIPTEX.0,
+        fixture::embedded_texture("CRATE01"),
+    )]);
+    fs::write(dir.join("FIXTURE.WAD"), data).unwrap();
+
+    let resolution = WadResolver::new([dir.clone()]).resolve(&bsp);
+    assert!(resolution.missing_wads.is_empty());
+    assert!(resolution.failed_wads.is_empty());
+    assert!(resolution.missing_textures.is_empty());
+    assert_eq!(resolution.textures.len(), 1);
+    assert_eq!(resolution.textures[0].index, 1);
+    assert_eq!(resolution.textures[0].texture.name(), "CRATE01");
+
+    fs::write(dir.join("FIXTURE.WAD"), b"WAD2").unwrap();
+    let failed = WadResolver::new([dir.clone()]).resolve(&bsp);
+    assert_eq!(failed.failed_wads.len(), 1);
+    assert_eq!(failed.missing_textures, vec![1]);
+
+    fs::remove_dir_all(&dir).unwrap();
+    let unresolved = WadResolver::new([dir]).resolve(&bsp);
+    assert_eq!(unresolved.missing_wads.len(), 1);
+    assert_eq!(unresolved.missing_textures, vec![1]);
+}
+
+#[test]
+fn test_wad_resolver_reports_every_wad() {
+    let mut bsp = Bsp::parse(&mut Cursor::new(fixture::map())).unwrap();
+    bsp.entities.0[0].set(
+        "wad",
+        "\\valve\\fixture.wad;\\valve\\broken.wad;\\valve\\gone.wad",
+    );
+
+    let dir = std::env::temp_dir().join(format!("bsp-lib-wads-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let data = wad(&[(
+        "CRATE01",
+        TYP_MIPTEX.0,
+        fixture::embedded_texture("CRATE01"),
+    )]);
+    fs::write(dir.join("fixture.wad"), data).unwrap();
+    fs::write(dir.join("broken.wad"), b"WAD3").unwrap();
+
+    let resolution = WadResolver::new([dir.clone()]).resolve(&bsp);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(resolution.textures.len(), 1);
+    assert!(resolution.missing_textures.is_empty());
+    assert_eq!(resolution.missing_wads, vec!["\\valve\\gone.wad"]);
+    assert_eq!(resolution.failed_wads.len(), 1);
+    assert!(resolution.failed_wads[0].path.ends_with("broken.wad"));
+}
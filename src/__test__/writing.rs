@@ -0,0 +1,275 @@
+use std::io::{Cursor, Seek};
+
+use crate::{
+    bsp::Bsp,
+    header::{BspHeader, BspLumpLayout, HEADER_LUMPS, LUMP_ENTITIES, LUMP_PLANES, LUMP_VERTICES},
+    lumps::{
+        bspx::BSPX_LMSHIFT,
+        entities::{BspEntitiesLump, BspEntity, BspEntityWriteError, MAX_KEY, MAX_VALUE},
+        vertices::BspVertex,
+    },
+    math::Vector3D,
+    parsing::decoding::BspParseError,
+};
+
+use super::{bspx::with_bspx, fixture};
+
+fn round_trip(data: &[u8]) -> Vec<u8> {
+    fixture::written(&Bsp::parse(&mut Cursor::new(data)).unwrap())
+}
+
+#[test]
+fn test_round_trip() {
+    let data = fixture::map();
+    assert_eq!(round_trip(&data), data);
+}
+
+#[test]
+fn test_round_trip_quake() {
+    let mut lumps = fixture::lumps();
+    lumps[8] = vec![10, 20, 30, 40, 50];
+    let face_end = lumps[7].len();
+    lumps[7][face_end - 4..].copy_from_slice(&2i32.to_le_bytes());
+    let data = fixture::assemble(29, &lumps);
+    assert_eq!(round_trip(&data), data);
+}
+
+#[test]
+fn test_round_trip_blue_shift() {
+    let mut lumps = fixture::lumps();
+    lumps.swap(LUMP_ENTITIES.0, LUMP_PLANES.0);
+    let data = fixture::assemble(30, &lumps);
+    assert_eq!(round_trip(&data), data);
+}
+
+#[test]
+fn test_round_trip_lump_order() {
+    let order: Vec<usize> = (0..15).rev().collect();
+    let data = fixture::assemble_in(30, &fixture::lumps(), &order);
+    assert_eq!(round_trip(&data), data);
+}
+
+#[test]
+fn test_round_trip_missing_texture() {
+    let mut lumps = fixture::lumps();
+    lumps[2][8..12].copy_from_slice(&(-1i32).to_le_bytes());
+    lumps[2].truncate(lumps[2].len() - 40);
+    let data = fixture::assemble(30, &lumps);
+    assert_eq!(round_trip(&data), data);
+}
+
+#[test]
+fn test_round_trip_bspx() {
+    let data = with_bspx(
+        fixture::map(),
+        &[(BSPX_LMSHIFT, vec![4]), ("CUSTOM", vec![1, 2, 3, 4, 5])],
+    );
+    assert_eq!(round_trip(&data), data);
+}
+
+#[test]
+fn test_round_trip_non_canonical() {
+    let mut lumps = fixture::lumps();
+    let text = String::from_utf8(lumps[LUMP_ENTITIES.0].clone()).unwrap();
+    let text = format!("// compiled by hand\r\n{}", text.replace('\n', "\r\n"));
+    lumps[LUMP_ENTITIES.0] = text.into_bytes();
+    let mut order: Vec<usize> = (1..HEADER_LUMPS).collect();
+    order.push(LUMP_ENTITIES.0);
+    let mut data = fixture::assemble_in(30, &lumps, &order);
+    // Unlike the compilers, leave the last lump unpadded.
+    let padding = lumps[LUMP_ENTITIES.0].len().next_multiple_of(4) - lumps[LUMP_ENTITIES.0].len();
+    data.truncate(data.len() - padding);
+
+    let written = round_trip(&data);
+    assert_ne!(written, data);
+    assert_eq!(round_trip(&written), written);
+    let bsp = Bsp::parse(&mut Cursor::new(&data)).unwrap();
+    let rewritten = Bsp::parse(&mut Cursor::new(&written)).unwrap();
+    assert_eq!(
+        format!("{:?}", rewritten.entities),
+        format!("{:?}", bsp.entities)
+    );
+    assert_eq!(other_lumps(&written), other_lumps(&data));
+}
+
+#[test]
+fn test_round_trip_quake_colored_lighting() {
+    let mut lumps = fixture::lumps();
+    lumps[8] = vec![10, 20, 30];
+    let data = fixture::assemble(29, &lumps);
+    let mut bsp = Bsp::parse(&mut Cursor::new(&data)).unwrap();
+    bsp.light_map.0[1].1 = 80;
+    let mut out = Cursor::new(vec![]);
+    bsp.write(&mut out).unwrap();
+    out.rewind().unwrap();
+    let written = Bsp::parse(&mut out).unwrap();
+    let samples: Vec<u8> = written.light_map.0.iter().map(|sample| sample.0).collect();
+    assert_eq!(samples, vec![10, 40, 30]);
+}
+
+#[test]
+fn test_write_modified() {
+    let mut bsp = Bsp::parse(&mut Cursor::new(fixture::map())).unwrap();
+    bsp.vertices.0.push(BspVertex(Vector3D {
+        x: 1.0,
+        y: 2.0,
+        z: 3.0,
+    }));
+    let mut out = Cursor::new(vec![]);
+    let header = bsp.write(&mut out).unwrap();
+    assert_eq!(header.lump[LUMP_VERTICES.0].n_length, 5 * 12);
+    assert_eq!(out.stream_position().unwrap() as usize, out.get_ref().len());
+
+    out.rewind().unwrap();
+    let written = Bsp::parse(&mut out).unwrap();
+    assert_eq!(written.layout, BspLumpLayout::Standard);
+    assert_eq!(written.vertices.0.len(), 5);
+    assert_eq!(written.vertices[4].0.z, 3.0);
+    assert_eq!(written.entities.0.len(), 2);
+    assert_eq!(written.textures[0].rgba(0), bsp.textures[0].rgba(0));
+}
+
+/// The bytes of every lump but the entities.
+fn other_lumps(data: &[u8]) -> Vec<Vec<u8>> {
+    let (header, _) = Bsp::extract_normalized_header(&mut Cursor::new(data)).unwrap();
+    (0..HEADER_LUMPS)
+        .filter(|&i| i != LUMP_ENTITIES.0)
+        .map(|i| {
+            let ptr = header.lump[i];
+            data[ptr.n_offset as usize..][..ptr.n_length as usize].to_vec()
+        })
+        .collect()
+}
+
+fn edit(entities: &mut BspEntitiesLump) {
+    entities[0].set("wad", "\\half-life\\valve\\halflife.wad");
+    entities.remove(1);
+    entities.push(BspEntity::from_iter([
+        ("classname", "info_target"),
+        ("targetname", "spot"),
+        ("origin", "128 64 -32"),
+    ]));
+}
+
+fn check_edited(data: &[u8], original: &[u8]) {
+    let bsp = Bsp::parse(&mut Cursor::new(data)).unwrap();
+    assert_eq!(bsp.entities.len(), 2);
+    assert_eq!(
+        bsp.entities[0].get("wad").unwrap(),
+        "\\half-life\\valve\\halflife.wad"
+    );
+    assert_eq!(bsp.entities[1].targetname().unwrap(), "spot");
+    assert_eq!(other_lumps(data), other_lumps(original));
+}
+
+#[test]
+fn test_write_edited_entities() {
+    let data = fixture::map();
+    let mut bsp = Bsp::parse(&mut Cursor::new(&data)).unwrap();
+    edit(&mut bsp.entities);
+    let mut out = Cursor::new(vec![]);
+    bsp.write(&mut out).unwrap();
+    check_edited(out.get_ref(), &data);
+}
+
+#[test]
+fn test_patch_entities() {
+    let order = [3, 9, 0, 7, 5, 1, 12, 2, 10, 4, 13, 6, 11, 8, 14];
+    let data = with_bspx(
+        fixture::assemble_in(30, &fixture::lumps(), &order),
+        &[(BSPX_LMSHIFT, vec![4])],
+    );
+    let bsp = Bsp::parse(&mut Cursor::new(&data)).unwrap();
+    let mut entities = bsp.entities.clone();
+    edit(&mut entities);
+    let mut out = vec![];
+    let header = Bsp::patch_entities(&mut Cursor::new(&data), &mut out, &entities).unwrap();
+    check_edited(&out, &data);
+
+    let text = entities.to_bytes().unwrap();
+    assert_eq!(text.last(), Some(&0));
+    assert_eq!(header.lump[LUMP_ENTITIES.0].n_length as usize, text.len());
+    let old_len = bsp.header.lump[LUMP_ENTITIES.0].n_length as usize;
+    let delta = text.len().next_multiple_of(4) as i32 - old_len.next_multiple_of(4) as i32;
+    assert_ne!(delta, 0);
+    let before: &BspHeader = bytemuck::from_bytes(&data[..124]);
+    for i in 0..HEADER_LUMPS {
+        let shifted = order.iter().position(|&j| j == i) > order.iter().position(|&j| j == 0);
+        let expected = before.lump[i].n_offset + if shifted { delta } else { 0 };
+        assert_eq!(header.lump[i].n_offset, expected, "lump {i}");
+    }
+    let patched = Bsp::parse(&mut Cursor::new(&out)).unwrap();
+    assert_eq!(
+        patched.bspx.unwrap().lm_shift.unwrap().0,
+        bsp.bspx.unwrap().lm_shift.unwrap().0
+    );
+}
+
+#[test]
+fn test_patch_entities_blue_shift() {
+    let mut lumps = fixture::lumps();
+    lumps.swap(LUMP_ENTITIES.0, LUMP_PLANES.0);
+    let data = fixture::assemble(30, &lumps);
+    let mut entities = Bsp::parse(&mut Cursor::new(&data)).unwrap().entities;
+    edit(&mut entities);
+    let mut out = vec![];
+    Bsp::patch_entities(&mut Cursor::new(&data), &mut out, &entities).unwrap();
+    check_edited(&out, &data);
+    let patched = Bsp::parse(&mut Cursor::new(&out)).unwrap();
+    assert_eq!(patched.layout, BspLumpLayout::BlueShift);
+}
+
+#[test]
+fn test_unwritable_entities() {
+    let data = fixture::map();
+    let bsp = Bsp::parse(&mut Cursor::new(&data)).unwrap();
+    let mut entities = bsp.entities.clone();
+    entities[1].set("message", "say \"hi\"");
+    let mut out = vec![];
+    let err = Bsp::patch_entities(&mut Cursor::new(&data), &mut out, &entities).unwrap_err();
+    let BspParseError::EntityLumpWriteError(err) = err else {
+        panic!("expected an entity write error, got {err:?}");
+    };
+    assert_eq!(
+        err,
+        BspEntityWriteError::BadCharacter {
+            entity: 1,
+            key: "message".to_owned(),
+            byte: b'"',
+        }
+    );
+    assert!(out.is_empty());
+
+    let mut bsp = bsp;
+    bsp.entities = entities;
+    let mut out = Cursor::new(vec![]);
+    assert!(bsp.write(&mut out).is_err());
+    assert!(out.get_ref().is_empty());
+
+    let error = |key: &str, value: &str| {
+        let mut entities =
+            BspEntitiesLump(vec![BspEntity::from_iter([("classname", "info_null")])]);
+        entities[0].push(key, value);
+        entities.to_bytes().unwrap_err()
+    };
+    assert!(matches!(
+        error("message", "two\nlines"),
+        BspEntityWriteError::BadCharacter { byte: b'\n', .. }
+    ));
+    assert!(matches!(
+        error("nul\0", "value"),
+        BspEntityWriteError::BadCharacter { byte: 0, .. }
+    ));
+    assert_eq!(
+        error(&"k".repeat(MAX_KEY + 1), "value"),
+        BspEntityWriteError::KeyLength {
+            entity: 0,
+            key: "k".repeat(MAX_KEY + 1),
+            found: MAX_KEY + 1,
+        }
+    );
+    assert!(matches!(
+        error("message", &"v".repeat(MAX_VALUE + 1)),
+        BspEntityWriteError::ValueLength { found, .. } if found == MAX_VALUE + 1
+    ));
+}
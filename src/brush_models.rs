@@ -0,0 +1,170 @@
+use std::fmt;
+
+use crate::{
+    bsp::Bsp,
+    lumps::{
+        entities::{BspEntity, BspEntityValueError},
+        faces::BspFace,
+        models::BspModel,
+    },
+    math::Vector3D,
+    relational::RelationError,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BrushModelError {
+    /// The `model` or `origin` key can't be read.
+    Value(BspEntityValueError),
+    /// The model number is out of the model lump.
+    Relation(RelationError),
+}
+
+impl fmt::Display for BrushModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Value(err) => write!(f, "{err}"),
+            Self::Relation(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for BrushModelError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Value(err) => Some(err),
+            Self::Relation(err) => Some(err),
+        }
+    }
+}
+
+impl From<BspEntityValueError> for BrushModelError {
+    fn from(err: BspEntityValueError) -> Self {
+        Self::Value(err)
+    }
+}
+
+impl From<RelationError> for BrushModelError {
+    fn from(err: RelationError) -> Self {
+        Self::Relation(err)
+    }
+}
+
+/// # Brush model of an entity
+///
+/// A submodel along with the entity using it. The vertices of a model are
+/// relative to `origin`: the compilers move the brushes of an entity with
+/// an origin brush around it, so that doors and trains rotate about it,
+/// and write it to the `origin` key. Other entities have no such key and
+/// their models are already in world space.
+#[derive(Debug, Clone, Copy)]
+pub struct BrushModel<'a> {
+    pub entity: &'a BspEntity,
+    /// Index of the model in the model lump.
+    pub index: usize,
+    pub model: &'a BspModel,
+    /// The `origin` of the entity, the pivot of the model.
+    pub origin: Vector3D,
+}
+
+impl<'a> BrushModel<'a> {
+    /// The faces of the model, failing if its range is out of the face
+    /// lump.
+    pub fn faces(&self, bsp: &'a Bsp) -> Result<&'a [BspFace], RelationError> {
+        self.model.try_faces(bsp)
+    }
+
+    /// Moves a point of the model to where the entity places it.
+    pub fn to_world(&self, point: Vector3D) -> Vector3D {
+        Vector3D {
+            x: point.x + self.origin.x,
+            y: point.y + self.origin.y,
+            z: point.z + self.origin.z,
+        }
+    }
+
+    /// The bounds of the model in world space, `(mins, maxs)`.
+    pub fn world_bounds(&self) -> (Vector3D, Vector3D) {
+        let [x, y, z] = self.model.n_mins;
+        let mins = self.to_world(Vector3D { x, y, z });
+        let [x, y, z] = self.model.n_maxs;
+        (mins, self.to_world(Vector3D { x, y, z }))
+    }
+}
+
+impl BspEntity {
+    /// # Model number
+    ///
+    /// The `N` of a `"model" "*N"` key, `None` when the entity has no model
+    /// or a studio or sprite one. `worldspawn` has no key and is model 0.
+    pub fn brush_model_index(&self) -> Option<Result<usize, BspEntityValueError>> {
+        let Some(value) = self.get("model") else {
+            return (self.classname().as_deref() == Some("worldspawn")).then_some(Ok(0));
+        };
+        let text = value.to_str();
+        let number = text.trim().strip_prefix('*')?;
+        Some(number.parse().map_err(|_| BspEntityValueError {
+            key: "model".to_owned(),
+            value: value.to_string(),
+            expected: "a model number such as *1",
+        }))
+    }
+
+    /// The brush model of the entity, `None` if it isn't a brush entity.
+    pub fn brush_model<'a>(
+        &'a self,
+        bsp: &'a Bsp,
+    ) -> Option<Result<BrushModel<'a>, BrushModelError>> {
+        let index = self.brush_model_index()?;
+        Some(brush_model(bsp, self, index))
+    }
+}
+
+fn brush_model<'a>(
+    bsp: &'a Bsp,
+    entity: &'a BspEntity,
+    index: Result<usize, BspEntityValueError>,
+) -> Result<BrushModel<'a>, BrushModelError> {
+    let index = index?;
+    Ok(BrushModel {
+        entity,
+        index,
+        model: bsp.try_model(index)?,
+        origin: entity.origin()?,
+    })
+}
+
+impl Bsp {
+    /// # Brush entities
+    ///
+    /// Every entity using a brush model along with it, in entity order,
+    /// `worldspawn` included. Each one comes with the index of the entity
+    /// in the entity lump.
+    pub fn brush_models(
+        &self,
+    ) -> impl Iterator<Item = (usize, Result<BrushModel<'_>, BrushModelError>)> {
+        self.entities.iter().enumerate().filter_map(|(i, entity)| {
+            let index = entity.brush_model_index()?;
+            Some((i, brush_model(self, entity, index)))
+        })
+    }
+
+    /// The indices of the models no entity uses, which the game never
+    /// spawns. Model 0 is counted as used by `worldspawn`.
+    pub fn unreferenced_models(&self) -> Vec<usize> {
+        let mut used = vec![false; self.models.0.len()];
+        for index in self
+            .entities
+            .iter()
+            .filter_map(BspEntity::brush_model_index)
+        {
+            if let Some(used) = index.ok().and_then(|i| used.get_mut(i)) {
+                *used = true;
+            }
+        }
+        used.iter()
+            .enumerate()
+            .filter(|&(_, &used)| !used)
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
@@ -1,17 +1,22 @@
-use crate::lumps::{
-  clip_nodes::BspClipNodesLump,
-  entities::BspEntitiesLump,
-  faces::BspFacesLump,
-  leaves::BspLeavesLump,
-  light_map::BspLightMapLump,
-  models::BspModelsLump,
-  nodes::BspNodesLump,
-  planes::BspPlanesLump,
-  surfaces::{BspEdgesLump, BspMarkSurfacesLump, BspSurfEdgesLump},
-  tex_info::BspTexInfoLump,
-  textures::BspTexturesLump,
-  vertices::BspVerticesLump,
-  vis::BspVisLump,
+use crate::{
+  header::{BspHeader, BspLumpLayout, BspVersion},
+  lumps::{
+    bspx::BspxLumps,
+    clip_nodes::BspClipNodesLump,
+    entities::BspEntitiesLump,
+    faces::BspFacesLump,
+    leaves::BspLeavesLump,
+    light_map::BspLightMapLump,
+    models::BspModelsLump,
+    nodes::BspNodesLump,
+    planes::BspPlanesLump,
+    surfaces::{BspEdgesLump, BspMarkSurfacesLump, BspSurfEdgesLump},
+    tex_info::BspTexInfoLump,
+    textures::BspTexturesLump,
+    vertices::BspVerticesLump,
+    vis::BspVisLump,
+  },
+  parsing::entities::BspEntityParseError,
 };
 
 /// # BSP file data
@@ -27,7 +32,8 @@ use crate::lumps::{
 ///   but is much more similar to BSP30 than BSP29.
 /// - GoldSrc normally uses BSP30, as described on this page.
 /// - Half-Life: Blue Shift flips the positions of the plane and entity lumps. 
-///   The version number remains the same.
+///   The version number remains the same. These maps are detected and parsed
+///   transparently, see `BspLumpLayout`.
 ///   - Tip: BSPFix can be used to losslessly convert between standard BSP30 and 
 ///     Blue Shift BSPs.
 /// - Paranoia 2: Savior uses a modified version of BSP30, called BSP31. This 
@@ -35,13 +41,27 @@ use crate::lumps::{
 ///   without breaking compatibility (using BSPX lumps).
 /// - James Bond 007: Nightfire uses a modified version of BSP30, called BSP42.
 ///
+/// Besides BSP30, Quake's BSP29 can be read too, see `BspVersion`.
+///
 /// > **Confirm:**
 /// >
 /// > Are the formats used by Counter-Strike Neo, Counter-Strike Nexon: Studio, 
 /// > and Cry of Fear different from BSP30?
 #[derive(Debug)]
 pub struct Bsp {
+  /// The format the file was parsed from.
+  pub version: BspVersion,
+  /// The order the entity and plane lumps were found in.
+  pub layout: BspLumpLayout,
+  /// The header the file was parsed from, in the standard layout. It is not
+  /// updated when the lumps change, its lengths and offsets only describe
+  /// the parsed file. Writing keeps the lumps in the order of its offsets,
+  /// and returns the header that was actually written.
+  pub header: BspHeader,
   pub entities: BspEntitiesLump,
+  /// Errors skipped in the entity text, when parsed with
+  /// `ParseOptions::recover_entities`.
+  pub entity_diagnostics: Vec<BspEntityParseError>,
   pub planes: BspPlanesLump,
   pub textures: BspTexturesLump,
   pub vertices: BspVerticesLump,
@@ -56,4 +76,6 @@ pub struct Bsp {
   pub edges: BspEdgesLump,
   pub surf_edges: BspSurfEdgesLump,
   pub models: BspModelsLump,
+  /// Extension lumps found after the standard ones, if any.
+  pub bspx: Option<BspxLumps>,
 }
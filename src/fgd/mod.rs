@@ -0,0 +1,186 @@
+pub mod parser;
+pub mod validation;
+
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FgdClassKind {
+    /// `@BaseClass`, only there to be inherited from.
+    Base,
+    /// `@PointClass`, an entity placed at a point.
+    Point,
+    /// `@SolidClass`, an entity tied to brushes.
+    Solid,
+}
+
+/// A property of a class header other than `base()`, such as
+/// `size(-16 -16 0, 16 16 72)` or `studio("models/scientist.mdl")`. Each
+/// argument is kept as written, its words separated by single spaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FgdHelper {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FgdChoice {
+    pub value: String,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FgdFlag {
+    pub bit: u32,
+    pub label: String,
+    pub default: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FgdKeyType {
+    String,
+    Integer,
+    Float,
+    Choices(Vec<FgdChoice>),
+    Flags(Vec<FgdFlag>),
+    /// An RGB color from 0 to 255, lights add a brightness after it.
+    Color255,
+    /// An RGB color from 0 to 1.
+    Color1,
+    /// A path to a model.
+    Studio,
+    /// A path to a sprite.
+    Sprite,
+    Sound,
+    Decal,
+    /// The `targetname` of the entity.
+    TargetSource,
+    /// The `targetname` of another entity.
+    TargetDestination,
+    /// A type the parser doesn't know of, values are taken as strings.
+    Other(String),
+}
+
+impl FgdKeyType {
+    pub(crate) fn from_name(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "string" => Self::String,
+            "integer" => Self::Integer,
+            "float" => Self::Float,
+            "choices" => Self::Choices(vec![]),
+            "flags" => Self::Flags(vec![]),
+            "color255" => Self::Color255,
+            "color1" => Self::Color1,
+            "studio" => Self::Studio,
+            "sprite" => Self::Sprite,
+            "sound" => Self::Sound,
+            "decal" => Self::Decal,
+            "target_source" => Self::TargetSource,
+            "target_destination" => Self::TargetDestination,
+            _ => Self::Other(name.to_owned()),
+        }
+    }
+}
+
+/// # FGD keys
+///
+/// A key an entity of the class may have:
+///
+/// ```text
+/// health(integer) : "Health" : 100 : "Hit points"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FgdKey {
+    pub name: String,
+    pub kind: FgdKeyType,
+    pub display_name: Option<String>,
+    pub default: Option<String>,
+    pub description: Option<String>,
+}
+
+/// # FGD classes
+///
+/// An entity class of the game data:
+///
+/// ```text
+/// @PointClass base(Targetname) size(-16 -16 0, 16 16 72) = monster_scientist : "Scientist"
+/// [
+///     body(choices) : "Body" : -1 = [ -1 : "Random" 0 : "Glasses" ]
+/// ]
+/// ```
+///
+/// Only the keys declared by the class itself are in `keys`, see
+/// `Fgd::keys` for the inherited ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FgdClass {
+    pub kind: FgdClassKind,
+    pub name: String,
+    pub description: Option<String>,
+    /// The classes of `base()`, in order.
+    pub bases: Vec<String>,
+    pub helpers: Vec<FgdHelper>,
+    pub keys: Vec<FgdKey>,
+}
+
+impl FgdClass {
+    pub fn helper(&self, name: &str) -> Option<&FgdHelper> {
+        self.helpers
+            .iter()
+            .find(|helper| helper.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// # Forge Game Data
+///
+/// The entity definitions an editor loads for a game: the classes, their
+/// keys and the type of each key. `@include`d files are listed but not
+/// loaded, their classes can be added with `Fgd::extend`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fgd {
+    pub classes: Vec<FgdClass>,
+    pub includes: Vec<String>,
+}
+
+impl Fgd {
+    /// The class named `name`, the last one if it is defined more than once.
+    pub fn class(&self, name: &str) -> Option<&FgdClass> {
+        self.classes.iter().rev().find(|class| class.name == name)
+    }
+
+    /// Adds the classes of another file, like an `@include` would.
+    pub fn extend(&mut self, other: Fgd) {
+        self.classes.extend(other.classes);
+    }
+
+    /// # Inherited keys
+    ///
+    /// Every key of a class, the ones of its bases first, depth first in
+    /// `base()` order. A key declared again replaces the inherited one in
+    /// place. Unknown bases and inheritance cycles are ignored.
+    pub fn keys<'a>(&'a self, class: &'a FgdClass) -> Vec<&'a FgdKey> {
+        let mut keys = vec![];
+        self.collect_keys(class, &mut HashSet::new(), &mut keys);
+        keys
+    }
+
+    fn collect_keys<'a>(
+        &'a self,
+        class: &'a FgdClass,
+        visited: &mut HashSet<&'a str>,
+        keys: &mut Vec<&'a FgdKey>,
+    ) {
+        if !visited.insert(&class.name) {
+            return;
+        }
+        for base in &class.bases {
+            if let Some(base) = self.class(base) {
+                self.collect_keys(base, visited, keys);
+            }
+        }
+        for key in &class.keys {
+            match keys.iter_mut().find(|known| known.name == key.name) {
+                Some(known) => *known = key,
+                None => keys.push(key),
+            }
+        }
+    }
+}
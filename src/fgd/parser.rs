@@ -0,0 +1,443 @@
+use std::fmt;
+
+use super::{Fgd, FgdChoice, FgdClass, FgdClassKind, FgdFlag, FgdHelper, FgdKey, FgdKeyType};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FgdErrorKind {
+    /// Something else than what the grammar allows at this point.
+    Unexpected {
+        expected: &'static str,
+        found: String,
+    },
+    /// The text ends in the middle of a definition.
+    UnexpectedEnd { expected: &'static str },
+    /// A `@...Class` that isn't a base, point or solid class.
+    UnknownClassType(String),
+}
+
+/// An error in an FGD file, located by the 1-based line and column of the
+/// token where it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FgdParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: FgdErrorKind,
+}
+
+impl fmt::Display for FgdParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            FgdErrorKind::Unexpected { expected, found } => {
+                write!(f, "expected {expected}, found `{found}`")?
+            }
+            FgdErrorKind::UnexpectedEnd { expected } => {
+                write!(f, "expected {expected}, found the end of the file")?
+            }
+            FgdErrorKind::UnknownClassType(name) => write!(f, "unknown class type @{name}")?,
+        }
+        write!(f, " at line {}, column {}", self.line, self.column)
+    }
+}
+
+impl std::error::Error for FgdParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token<'a> {
+    /// `@` and the name after it.
+    Directive(&'a str),
+    Word(&'a str),
+    Quoted(&'a str),
+    Punct(char),
+}
+
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Directive(name) => write!(f, "@{name}"),
+            Token::Word(word) => write!(f, "{word}"),
+            Token::Quoted(text) => write!(f, "\"{text}\""),
+            Token::Punct(c) => write!(f, "{c}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Tokenizer<'a> {
+    text: &'a str,
+    position: usize,
+    line: usize,
+    line_start: usize,
+}
+
+const PUNCTUATION: &[u8] = b"()[]=:,+";
+
+impl<'a> Tokenizer<'a> {
+    fn skip_blank(&mut self) {
+        let bytes = self.text.as_bytes();
+        loop {
+            while let Some(&c) = bytes.get(self.position) {
+                if !c.is_ascii_whitespace() {
+                    break;
+                }
+                if c == b'\n' {
+                    self.line += 1;
+                    self.line_start = self.position + 1;
+                }
+                self.position += 1;
+            }
+            if !bytes[self.position..].starts_with(b"//") {
+                return;
+            }
+            while bytes.get(self.position).is_some_and(|&c| c != b'\n') {
+                self.position += 1;
+            }
+        }
+    }
+
+    /// The line and column of the next token.
+    fn location(&mut self) -> (usize, usize) {
+        self.skip_blank();
+        (self.line, self.position - self.line_start + 1)
+    }
+
+    fn is_word(c: u8) -> bool {
+        !c.is_ascii_whitespace() && c != b'"' && c != b'@' && !PUNCTUATION.contains(&c)
+    }
+
+    fn word(&mut self) -> &'a str {
+        let start = self.position;
+        let bytes = self.text.as_bytes();
+        while bytes.get(self.position).is_some_and(|&c| Self::is_word(c)) {
+            self.position += 1;
+        }
+        &self.text[start..self.position]
+    }
+
+    fn next_token(&mut self) -> Option<Token<'a>> {
+        self.skip_blank();
+        let bytes = self.text.as_bytes();
+        let &first = bytes.get(self.position)?;
+        let token = match first {
+            b'"' => {
+                let start = self.position + 1;
+                let end = bytes[start..]
+                    .iter()
+                    .position(|&c| c == b'"')
+                    .map_or(bytes.len(), |i| start + i);
+                for (i, &c) in bytes.iter().enumerate().take(end).skip(start) {
+                    if c == b'\n' {
+                        self.line += 1;
+                        self.line_start = i + 1;
+                    }
+                }
+                self.position = (end + 1).min(bytes.len());
+                Token::Quoted(&self.text[start..end])
+            }
+            b'@' => {
+                self.position += 1;
+                Token::Directive(self.word())
+            }
+            _ if PUNCTUATION.contains(&first) => {
+                self.position += 1;
+                Token::Punct(first as char)
+            }
+            _ => Token::Word(self.word()),
+        };
+        Some(token)
+    }
+
+    fn peek(&self) -> Option<Token<'a>> {
+        let mut copy = *self;
+        copy.next_token()
+    }
+}
+
+struct Parser<'a> {
+    tokens: Tokenizer<'a>,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&mut self, expected: &'static str, found: Option<Token>) -> FgdParseError {
+        let (line, column) = self.tokens.location();
+        let kind = match found {
+            Some(found) => FgdErrorKind::Unexpected {
+                expected,
+                found: found.to_string(),
+            },
+            None => FgdErrorKind::UnexpectedEnd { expected },
+        };
+        FgdParseError { line, column, kind }
+    }
+
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.peek()
+    }
+
+    fn is_next(&self, c: char) -> bool {
+        self.peek() == Some(Token::Punct(c))
+    }
+
+    fn expect(&mut self, c: char, expected: &'static str) -> Result<(), FgdParseError> {
+        match self.peek() {
+            Some(Token::Punct(found)) if found == c => {
+                self.tokens.next_token();
+                Ok(())
+            }
+            found => Err(self.error(expected, found)),
+        }
+    }
+
+    fn word(&mut self, expected: &'static str) -> Result<&'a str, FgdParseError> {
+        match self.peek() {
+            Some(Token::Word(word)) => {
+                self.tokens.next_token();
+                Ok(word)
+            }
+            found => Err(self.error(expected, found)),
+        }
+    }
+
+    /// A quoted string, joining the ones concatenated with `+`.
+    fn quoted(&mut self, expected: &'static str) -> Result<String, FgdParseError> {
+        let Some(Token::Quoted(text)) = self.peek() else {
+            let found = self.peek();
+            return Err(self.error(expected, found));
+        };
+        self.tokens.next_token();
+        let mut text = text.to_owned();
+        while self.is_next('+') {
+            self.tokens.next_token();
+            text.push_str(&self.quoted(expected)?);
+        }
+        Ok(text)
+    }
+
+    /// A value that may be quoted or not, such as a default or a choice.
+    fn value(&mut self, expected: &'static str) -> Result<String, FgdParseError> {
+        match self.peek() {
+            Some(Token::Quoted(_)) => self.quoted(expected),
+            Some(Token::Word(word)) => {
+                self.tokens.next_token();
+                Ok(word.to_owned())
+            }
+            found => Err(self.error(expected, found)),
+        }
+    }
+
+    /// `( args )`, splitting the arguments at commas.
+    fn args(&mut self) -> Result<Vec<String>, FgdParseError> {
+        self.expect('(', "`(`")?;
+        let mut args = vec![];
+        let mut words: Vec<String> = vec![];
+        loop {
+            match self.tokens.next_token() {
+                Some(Token::Punct(')')) => break,
+                Some(Token::Punct(',')) => args.push(std::mem::take(&mut words).join(" ")),
+                Some(Token::Word(word)) => words.push(word.to_owned()),
+                Some(Token::Quoted(text)) => words.push(text.to_owned()),
+                found => return Err(self.error("`)`", found)),
+            }
+        }
+        if !words.is_empty() || !args.is_empty() {
+            args.push(words.join(" "));
+        }
+        Ok(args)
+    }
+
+    /// Skips the tokens of a directive this parser doesn't know of, up to
+    /// the next directive.
+    fn skip_directive(&mut self) {
+        let mut depth = 0usize;
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Directive(_) if depth == 0 => return,
+                Token::Punct('[' | '(') => depth += 1,
+                Token::Punct(']' | ')') => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            self.tokens.next_token();
+        }
+    }
+
+    fn class(&mut self, kind: FgdClassKind) -> Result<FgdClass, FgdParseError> {
+        let mut bases = vec![];
+        let mut helpers = vec![];
+        while let Some(Token::Word(name)) = self.peek() {
+            self.tokens.next_token();
+            let args = self.args()?;
+            if name.eq_ignore_ascii_case("base") {
+                bases.extend(args);
+            } else {
+                helpers.push(FgdHelper {
+                    name: name.to_owned(),
+                    args,
+                });
+            }
+        }
+        self.expect('=', "`=` or a class property")?;
+        let name = self.word("a class name")?.to_owned();
+        let mut description = None;
+        if self.is_next(':') {
+            self.tokens.next_token();
+            description = Some(self.quoted("a class description")?);
+        }
+        self.expect('[', "`[`")?;
+        let mut keys = vec![];
+        while !self.is_next(']') {
+            keys.push(self.key()?);
+        }
+        self.tokens.next_token();
+        Ok(FgdClass {
+            kind,
+            name,
+            description,
+            bases,
+            helpers,
+            keys,
+        })
+    }
+
+    fn key(&mut self) -> Result<FgdKey, FgdParseError> {
+        let name = self.word("a key or `]`")?.to_owned();
+        self.expect('(', "`(`")?;
+        let mut kind = FgdKeyType::from_name(self.word("a key type")?);
+        self.expect(')', "`)`")?;
+        // Modifiers such as `readonly`.
+        while matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case("readonly") || word.eq_ignore_ascii_case("report"))
+        {
+            self.tokens.next_token();
+        }
+        let mut fields: [Option<String>; 3] = [None, None, None];
+        for field in fields.iter_mut() {
+            if !self.is_next(':') {
+                break;
+            }
+            self.tokens.next_token();
+            if matches!(self.peek(), Some(Token::Quoted(_) | Token::Word(_)))
+                && !self.is_key_start()
+            {
+                *field = Some(self.value("a key field")?);
+            }
+        }
+        let [display_name, default, description] = fields;
+        if self.is_next('=') {
+            self.tokens.next_token();
+            self.expect('[', "`[`")?;
+            match &mut kind {
+                FgdKeyType::Flags(flags) => {
+                    while !self.is_next(']') {
+                        flags.push(self.flag()?);
+                    }
+                }
+                FgdKeyType::Choices(choices) => {
+                    while !self.is_next(']') {
+                        let value = self.value("a choice value or `]`")?;
+                        self.expect(':', "`:`")?;
+                        let label = self.quoted("a choice label")?;
+                        choices.push(FgdChoice { value, label });
+                    }
+                }
+                _ => {
+                    let found = self.peek();
+                    return Err(self.error("a key of type choices or flags", found));
+                }
+            }
+            self.tokens.next_token();
+        }
+        Ok(FgdKey {
+            name,
+            kind,
+            display_name,
+            default,
+            description,
+        })
+    }
+
+    /// Whether the next tokens are `name(`, the start of the next key, in
+    /// which case the field before was left empty.
+    fn is_key_start(&self) -> bool {
+        let mut copy = self.tokens;
+        matches!(copy.next_token(), Some(Token::Word(_)))
+            && copy.next_token() == Some(Token::Punct('('))
+    }
+
+    fn flag(&mut self) -> Result<FgdFlag, FgdParseError> {
+        let found = self.peek();
+        let Some(Ok(bit)) = self.word("a flag bit or `]`").ok().map(str::parse) else {
+            return Err(self.error("a flag bit or `]`", found));
+        };
+        self.expect(':', "`:`")?;
+        let label = self.quoted("a flag label")?;
+        let mut default = false;
+        if self.is_next(':') {
+            self.tokens.next_token();
+            default = self.word("a flag default")? != "0";
+        }
+        Ok(FgdFlag {
+            bit,
+            label,
+            default,
+        })
+    }
+
+    fn fgd(&mut self) -> Result<Fgd, FgdParseError> {
+        let mut fgd = Fgd::default();
+        loop {
+            let (line, column) = self.tokens.location();
+            let Some(token) = self.tokens.next_token() else {
+                break;
+            };
+            let Token::Directive(directive) = token else {
+                return Err(FgdParseError {
+                    line,
+                    column,
+                    kind: FgdErrorKind::Unexpected {
+                        expected: "a directive such as @PointClass",
+                        found: token.to_string(),
+                    },
+                });
+            };
+            let kind = match directive.to_ascii_lowercase().as_str() {
+                "baseclass" => FgdClassKind::Base,
+                "pointclass" => FgdClassKind::Point,
+                "solidclass" => FgdClassKind::Solid,
+                "include" => {
+                    let file = self.quoted("a file name")?;
+                    fgd.includes.push(file);
+                    continue;
+                }
+                name if name.ends_with("class") => {
+                    return Err(FgdParseError {
+                        line,
+                        column,
+                        kind: FgdErrorKind::UnknownClassType(directive.to_owned()),
+                    });
+                }
+                _ => {
+                    self.skip_directive();
+                    continue;
+                }
+            };
+            fgd.classes.push(self.class(kind)?);
+        }
+        Ok(fgd)
+    }
+}
+
+impl Fgd {
+    /// # FGD parsing
+    ///
+    /// Parses the text of an FGD file. Directives other than the classes
+    /// and `@include`, such as `@mapsize`, are skipped.
+    pub fn parse(text: &str) -> Result<Self, FgdParseError> {
+        Parser {
+            tokens: Tokenizer {
+                text,
+                position: 0,
+                line: 1,
+                line_start: 0,
+            },
+        }
+        .fgd()
+    }
+}
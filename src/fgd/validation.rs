@@ -0,0 +1,301 @@
+use std::fmt;
+
+use crate::lumps::entities::{BspEntitiesLump, BspEntity};
+
+use super::{Fgd, FgdClass, FgdKey, FgdKeyType};
+
+/// Keys the compilers and the engine handle for every entity, which game
+/// data files usually leave out.
+const IMPLICIT_KEYS: [&str; 6] = [
+    "classname",
+    "origin",
+    "model",
+    "spawnflags",
+    "wad",
+    "mapversion",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FgdIssueKind {
+    /// No class of the game data has the classname of the entity, or it
+    /// has no classname at all.
+    UnknownClass(Option<String>),
+    /// The class has no such key.
+    UnknownKey(String),
+    /// The value can't be read as the type of its key.
+    WrongType {
+        key: String,
+        value: String,
+        expected: &'static str,
+    },
+    /// The value isn't one of the choices of its key.
+    InvalidChoice { key: String, value: String },
+    /// Bits of `spawnflags` that no flag of the class defines.
+    UndefinedFlags(u32),
+}
+
+/// A mismatch between an entity of a map and its class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FgdIssue {
+    /// Index of the entity in the entity lump.
+    pub entity: usize,
+    pub kind: FgdIssueKind,
+}
+
+impl fmt::Display for FgdIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "entity {}: ", self.entity)?;
+        match &self.kind {
+            FgdIssueKind::UnknownClass(Some(classname)) => {
+                write!(f, "unknown class {classname:?}")
+            }
+            FgdIssueKind::UnknownClass(None) => write!(f, "no classname"),
+            FgdIssueKind::UnknownKey(key) => write!(f, "unknown key {key:?}"),
+            FgdIssueKind::WrongType {
+                key,
+                value,
+                expected,
+            } => write!(f, "bad value {value:?} for {key:?}, expected {expected}"),
+            FgdIssueKind::InvalidChoice { key, value } => {
+                write!(f, "{value:?} is not one of the choices of {key:?}")
+            }
+            FgdIssueKind::UndefinedFlags(bits) => {
+                write!(f, "spawnflags bits {bits:#x} are not defined")
+            }
+        }
+    }
+}
+
+/// A value of an entity, read as the type of its key.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FgdValue {
+    /// Strings, paths, sounds, decals and target names.
+    String(String),
+    Integer(i64),
+    Float(f32),
+    /// A choice, along with its label.
+    Choice {
+        value: String,
+        label: String,
+    },
+    /// The `spawnflags` bits, along with the labels of the set ones.
+    Flags {
+        bits: u32,
+        labels: Vec<String>,
+    },
+    Color255 {
+        rgb: [u8; 3],
+        /// The brightness lights add after the color.
+        brightness: Option<f32>,
+    },
+    Color1([f32; 3]),
+}
+
+/// # Typed entity
+///
+/// The values of an entity for every key of its class, in the order of
+/// the class. Keys the entity doesn't set take their default, and are left
+/// out when there's none.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FgdEntity {
+    pub classname: String,
+    pub values: Vec<(String, FgdValue)>,
+}
+
+impl FgdEntity {
+    pub fn get(&self, key: &str) -> Option<&FgdValue> {
+        self.values
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+    }
+}
+
+fn wrong_type(key: &FgdKey, value: &str, expected: &'static str) -> FgdIssueKind {
+    FgdIssueKind::WrongType {
+        key: key.name.clone(),
+        value: value.to_owned(),
+        expected,
+    }
+}
+
+fn same_value(a: &str, b: &str) -> bool {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a.trim() == b.trim(),
+    }
+}
+
+/// Reads a value as the type of its key.
+fn decode_value(key: &FgdKey, value: &str) -> Result<FgdValue, FgdIssueKind> {
+    let numbers = |expected| {
+        value
+            .split_whitespace()
+            .map(str::parse::<f32>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| wrong_type(key, value, expected))
+    };
+    Ok(match &key.kind {
+        FgdKeyType::Integer => FgdValue::Integer(
+            value
+                .trim()
+                .parse()
+                .map_err(|_| wrong_type(key, value, "an integer"))?,
+        ),
+        FgdKeyType::Float => FgdValue::Float(
+            value
+                .trim()
+                .parse()
+                .map_err(|_| wrong_type(key, value, "a number"))?,
+        ),
+        FgdKeyType::Choices(choices) => {
+            let Some(choice) = choices
+                .iter()
+                .find(|choice| same_value(&choice.value, value))
+            else {
+                return Err(FgdIssueKind::InvalidChoice {
+                    key: key.name.clone(),
+                    value: value.to_owned(),
+                });
+            };
+            FgdValue::Choice {
+                value: choice.value.clone(),
+                label: choice.label.clone(),
+            }
+        }
+        FgdKeyType::Flags(flags) => {
+            let bits: u32 = value
+                .trim()
+                .parse()
+                .map_err(|_| wrong_type(key, value, "an unsigned integer"))?;
+            let defined = flags.iter().fold(0, |defined, flag| defined | flag.bit);
+            if bits & !defined != 0 {
+                return Err(FgdIssueKind::UndefinedFlags(bits & !defined));
+            }
+            FgdValue::Flags {
+                bits,
+                labels: flags
+                    .iter()
+                    .filter(|flag| bits & flag.bit != 0)
+                    .map(|flag| flag.label.clone())
+                    .collect(),
+            }
+        }
+        FgdKeyType::Color255 => {
+            const EXPECTED: &str = "3 integers from 0 to 255 and a brightness";
+            let components = numbers(EXPECTED)?;
+            if !(3..=4).contains(&components.len())
+                || components[..3]
+                    .iter()
+                    .any(|&c| c.fract() != 0.0 || !(0.0..=255.0).contains(&c))
+            {
+                return Err(wrong_type(key, value, EXPECTED));
+            }
+            FgdValue::Color255 {
+                rgb: [
+                    components[0] as u8,
+                    components[1] as u8,
+                    components[2] as u8,
+                ],
+                brightness: components.get(3).copied(),
+            }
+        }
+        FgdKeyType::Color1 => {
+            let components: [f32; 3] = numbers("3 numbers")?
+                .try_into()
+                .map_err(|_| wrong_type(key, value, "3 numbers"))?;
+            FgdValue::Color1(components)
+        }
+        _ => FgdValue::String(value.to_owned()),
+    })
+}
+
+impl Fgd {
+    /// The class of an entity.
+    pub fn class_of(&self, entity: &BspEntity) -> Result<&FgdClass, FgdIssueKind> {
+        let classname = entity.classname();
+        classname
+            .as_deref()
+            .and_then(|classname| self.class(classname))
+            .ok_or_else(|| FgdIssueKind::UnknownClass(classname.map(|c| c.into_owned())))
+    }
+
+    fn check_entity(&self, entity: &BspEntity) -> Vec<FgdIssueKind> {
+        let class = match self.class_of(entity) {
+            Ok(class) => class,
+            Err(issue) => return vec![issue],
+        };
+        let keys = self.keys(class);
+        let mut issues = vec![];
+        for (key, value) in entity.iter() {
+            let key = key.to_str();
+            match keys.iter().find(|known| known.name == key) {
+                Some(known) => {
+                    if let Err(issue) = decode_value(known, &value.to_str()) {
+                        issues.push(issue);
+                    }
+                }
+                None if key == "spawnflags" => {
+                    let flags = FgdKey {
+                        name: "spawnflags".to_owned(),
+                        kind: FgdKeyType::Flags(vec![]),
+                        display_name: None,
+                        default: None,
+                        description: None,
+                    };
+                    if let Err(issue) = decode_value(&flags, &value.to_str()) {
+                        issues.push(issue);
+                    }
+                }
+                None if IMPLICIT_KEYS.contains(&key.as_ref()) => {}
+                None => issues.push(FgdIssueKind::UnknownKey(key.into_owned())),
+            }
+        }
+        issues
+    }
+
+    /// # Entity validation
+    ///
+    /// Checks every entity against its class: the classname must be known,
+    /// each key declared by the class or one of its bases, and each value
+    /// of the type of its key. Keys every entity may have, like `origin`,
+    /// don't need to be declared.
+    pub fn validate(&self, entities: &BspEntitiesLump) -> Vec<FgdIssue> {
+        entities
+            .0
+            .iter()
+            .enumerate()
+            .flat_map(|(i, entity)| {
+                self.check_entity(entity)
+                    .into_iter()
+                    .map(move |kind| FgdIssue { entity: i, kind })
+            })
+            .collect()
+    }
+
+    /// Reads the values of an entity as the types of its class, failing on
+    /// the first value that doesn't fit. Missing flags take the flags that
+    /// are on by default.
+    pub fn decode(&self, entity: &BspEntity) -> Result<FgdEntity, FgdIssueKind> {
+        let class = self.class_of(entity)?;
+        let mut values = vec![];
+        for key in self.keys(class) {
+            let value = match (entity.get(&key.name), &key.default, &key.kind) {
+                (Some(value), _, _) => value.to_str(),
+                (None, Some(default), _) => default.into(),
+                (None, None, FgdKeyType::Flags(flags)) => flags
+                    .iter()
+                    .filter(|flag| flag.default)
+                    .fold(0, |bits, flag| bits | flag.bit)
+                    .to_string()
+                    .into(),
+                (None, None, _) => continue,
+            };
+            values.push((key.name.clone(), decode_value(key, &value)?));
+        }
+        Ok(FgdEntity {
+            classname: class.name.clone(),
+            values,
+        })
+    }
+}
@@ -1,5 +1,6 @@
 use bytemuck::{Pod, Zeroable};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct LumpType(pub usize);
 pub const LUMP_ENTITIES: LumpType = LumpType(0);
 pub const LUMP_PLANES: LumpType = LumpType(1);
@@ -17,6 +18,39 @@ pub const LUMP_EDGES: LumpType = LumpType(12);
 pub const LUMP_SURFEDGES: LumpType = LumpType(13);
 pub const LUMP_MODELS: LumpType = LumpType(14);
 pub const HEADER_LUMPS: usize = 15;
+/// A lump that isn't one of the standard ones, named `unknown`.
+pub const LUMP_UNKNOWN: LumpType = LumpType(HEADER_LUMPS);
+
+pub const LUMP_NAMES: [&str; HEADER_LUMPS] = [
+    "entities",
+    "planes",
+    "textures",
+    "vertices",
+    "visibility",
+    "nodes",
+    "texinfo",
+    "faces",
+    "lighting",
+    "clipnodes",
+    "leaves",
+    "marksurfaces",
+    "edges",
+    "surfedges",
+    "models",
+];
+
+impl LumpType {
+    /// Lower case name of the lump, as used by the compilers' logs.
+    pub fn name(&self) -> &'static str {
+        LUMP_NAMES.get(self.0).copied().unwrap_or("unknown")
+    }
+}
+
+impl std::fmt::Display for LumpType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} lump", self.name())
+    }
+}
 
 #[derive(Debug, Clone, Copy, Pod, Zeroable)]
 #[repr(C)]
@@ -131,3 +165,76 @@ pub struct BspHeader {
     pub n_version: i32,
     pub lump: [BspLumpPointer; HEADER_LUMPS],
 }
+
+impl BspHeader {
+    /// The format of the file, if it is a supported one.
+    pub fn version(&self) -> Option<BspVersion> {
+        BspVersion::from_raw(self.n_version)
+    }
+
+    /// Returns a copy of this header with its lumps reordered from or to the
+    /// given layout. Swapping is its own inverse, so the same call converts a
+    /// Blue Shift header to the standard order and back.
+    pub fn with_layout(&self, layout: BspLumpLayout) -> BspHeader {
+        let mut header = *self;
+        if layout == BspLumpLayout::BlueShift {
+            header.lump.swap(LUMP_ENTITIES.0, LUMP_PLANES.0);
+        }
+        header
+    }
+}
+
+/// # Lump layouts
+///
+/// Half-Life: Blue Shift keeps the version number 30, but swaps the pointers
+/// of the entity and plane lumps in the header. There's no flag for it, so the
+/// layout has to be guessed from the contents of both lumps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BspLumpLayout {
+    #[default]
+    Standard,
+    BlueShift,
+}
+
+/// # BSP versions
+///
+/// The formats this library can read. Quake's BSP29 shares the lump layout of
+/// BSP30, except for its lighting, which stores one brightness byte per
+/// sample instead of an RGB triple, and for its textures, which carry no
+/// palette as Quake uses a single, global one (`gfx/palette.lmp`).
+///
+/// Lighting is expanded to RGB when parsing BSP29 files, and face lightmap
+/// offsets are scaled to match, so both formats look alike once parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BspVersion {
+    /// Quake, version 29.
+    Quake29,
+    /// GoldSrc, version 30.
+    GoldSrc30,
+}
+impl BspVersion {
+    pub const SUPPORTED: [BspVersion; 2] = [BspVersion::Quake29, BspVersion::GoldSrc30];
+
+    pub fn from_raw(n_version: i32) -> Option<Self> {
+        Self::SUPPORTED
+            .into_iter()
+            .find(|version| version.raw() == n_version)
+    }
+
+    pub fn raw(self) -> i32 {
+        match self {
+            BspVersion::Quake29 => 29,
+            BspVersion::GoldSrc30 => 30,
+        }
+    }
+
+    /// Whether lighting is stored as one byte per sample.
+    pub fn has_mono_lighting(self) -> bool {
+        self == BspVersion::Quake29
+    }
+
+    /// Whether embedded textures are followed by their own palette.
+    pub fn has_texture_palettes(self) -> bool {
+        self == BspVersion::GoldSrc30
+    }
+}
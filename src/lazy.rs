@@ -0,0 +1,180 @@
+use std::{
+    cell::{OnceCell, RefCell},
+    io::{Read, Seek},
+};
+
+use crate::{
+    bsp::Bsp,
+    header::{BspHeader, BspLumpLayout, BspVersion},
+    lumps::{
+        bspx::BspxLumps,
+        clip_nodes::BspClipNodesLump,
+        entities::BspEntitiesLump,
+        faces::BspFacesLump,
+        leaves::BspLeavesLump,
+        light_map::BspLightMapLump,
+        models::BspModelsLump,
+        nodes::BspNodesLump,
+        planes::BspPlanesLump,
+        surfaces::{BspEdgesLump, BspMarkSurfacesLump, BspSurfEdgesLump},
+        tex_info::BspTexInfoLump,
+        textures::BspTexturesLump,
+        vertices::BspVerticesLump,
+        vis::BspVisLump,
+    },
+    parsing::{
+        decoding::{BspParseError, LumpExtractor, PtrLumpReader},
+        options::ParseOptions,
+    },
+};
+
+/// # Lazy BSP
+///
+/// Reads the header when opened, and every lump the first time it is
+/// accessed. Decoded lumps are cached, so later accesses don't touch the
+/// reader again. The accessors mirror the fields of `Bsp`, and decode the
+/// lumps the same way `Bsp::parse` does.
+///
+/// A lump that fails to decode is not cached, accessing it again retries.
+#[derive(Debug)]
+pub struct LazyBsp<R: Read + Seek> {
+    read: RefCell<R>,
+    pub version: BspVersion,
+    pub layout: BspLumpLayout,
+    /// The header in the standard layout.
+    pub header: BspHeader,
+    entities: OnceCell<BspEntitiesLump>,
+    planes: OnceCell<BspPlanesLump>,
+    textures: OnceCell<BspTexturesLump>,
+    vertices: OnceCell<BspVerticesLump>,
+    vis: OnceCell<BspVisLump>,
+    nodes: OnceCell<BspNodesLump>,
+    tex_info: OnceCell<BspTexInfoLump>,
+    faces: OnceCell<BspFacesLump>,
+    light_map: OnceCell<BspLightMapLump>,
+    clip_nodes: OnceCell<BspClipNodesLump>,
+    leaves: OnceCell<BspLeavesLump>,
+    mark_surfaces: OnceCell<BspMarkSurfacesLump>,
+    edges: OnceCell<BspEdgesLump>,
+    surf_edges: OnceCell<BspSurfEdgesLump>,
+    models: OnceCell<BspModelsLump>,
+    bspx: OnceCell<Option<BspxLumps>>,
+}
+
+impl<R: Read + Seek> LazyBsp<R> {
+    /// Reads the header and detects its layout, without reading any lump.
+    pub fn open(mut read: R) -> Result<Self, BspParseError> {
+        let (header, layout) = Bsp::extract_normalized_header(&mut read)?;
+        Ok(Self {
+            read: RefCell::new(read),
+            version: header.version().unwrap_or(BspVersion::GoldSrc30),
+            layout,
+            header,
+            entities: OnceCell::new(),
+            planes: OnceCell::new(),
+            textures: OnceCell::new(),
+            vertices: OnceCell::new(),
+            vis: OnceCell::new(),
+            nodes: OnceCell::new(),
+            tex_info: OnceCell::new(),
+            faces: OnceCell::new(),
+            light_map: OnceCell::new(),
+            clip_nodes: OnceCell::new(),
+            leaves: OnceCell::new(),
+            mark_surfaces: OnceCell::new(),
+            edges: OnceCell::new(),
+            surf_edges: OnceCell::new(),
+            models: OnceCell::new(),
+            bspx: OnceCell::new(),
+        })
+    }
+
+    /// Gives the reader back, dropping every cached lump.
+    pub fn into_inner(self) -> R {
+        self.read.into_inner()
+    }
+
+    fn load<'a, Lump>(&self, cell: &'a OnceCell<Lump>) -> Result<&'a Lump, BspParseError>
+    where
+        Lump: PtrLumpReader,
+        BspHeader: LumpExtractor<Lump>,
+    {
+        if let Some(lump) = cell.get() {
+            return Ok(lump);
+        }
+        let lump = self.header.extract_lump(&mut *self.read.borrow_mut())?;
+        Ok(cell.get_or_init(|| lump))
+    }
+
+    pub fn entities(&self) -> Result<&BspEntitiesLump, BspParseError> {
+        self.load(&self.entities)
+    }
+
+    pub fn planes(&self) -> Result<&BspPlanesLump, BspParseError> {
+        self.load(&self.planes)
+    }
+
+    pub fn textures(&self) -> Result<&BspTexturesLump, BspParseError> {
+        self.load(&self.textures)
+    }
+
+    pub fn vertices(&self) -> Result<&BspVerticesLump, BspParseError> {
+        self.load(&self.vertices)
+    }
+
+    pub fn vis(&self) -> Result<&BspVisLump, BspParseError> {
+        self.load(&self.vis)
+    }
+
+    pub fn nodes(&self) -> Result<&BspNodesLump, BspParseError> {
+        self.load(&self.nodes)
+    }
+
+    pub fn tex_info(&self) -> Result<&BspTexInfoLump, BspParseError> {
+        self.load(&self.tex_info)
+    }
+
+    pub fn faces(&self) -> Result<&BspFacesLump, BspParseError> {
+        self.load(&self.faces)
+    }
+
+    pub fn light_map(&self) -> Result<&BspLightMapLump, BspParseError> {
+        self.load(&self.light_map)
+    }
+
+    pub fn clip_nodes(&self) -> Result<&BspClipNodesLump, BspParseError> {
+        self.load(&self.clip_nodes)
+    }
+
+    pub fn leaves(&self) -> Result<&BspLeavesLump, BspParseError> {
+        self.load(&self.leaves)
+    }
+
+    pub fn mark_surfaces(&self) -> Result<&BspMarkSurfacesLump, BspParseError> {
+        self.load(&self.mark_surfaces)
+    }
+
+    pub fn edges(&self) -> Result<&BspEdgesLump, BspParseError> {
+        self.load(&self.edges)
+    }
+
+    pub fn surf_edges(&self) -> Result<&BspSurfEdgesLump, BspParseError> {
+        self.load(&self.surf_edges)
+    }
+
+    pub fn models(&self) -> Result<&BspModelsLump, BspParseError> {
+        self.load(&self.models)
+    }
+
+    pub fn bspx(&self) -> Result<Option<&BspxLumps>, BspParseError> {
+        if let Some(bspx) = self.bspx.get() {
+            return Ok(bspx.as_ref());
+        }
+        let bspx = Bsp::extract_bspx(
+            &mut *self.read.borrow_mut(),
+            &self.header,
+            &ParseOptions::unlimited(),
+        )?;
+        Ok(self.bspx.get_or_init(|| bspx).as_ref())
+    }
+}
@@ -2,8 +2,18 @@ pub mod math;
 pub mod header;
 pub mod lumps;
 pub mod bsp;
+pub mod brush_models;
+pub mod fgd;
+pub mod lazy;
+pub mod limits;
 pub mod parsing;
 pub mod relational;
+pub mod tree;
+pub mod triggers;
+pub mod validation;
+pub mod view;
+pub mod wad;
+pub mod writing;
 
 #[cfg(test)]
 mod __test__;
@@ -0,0 +1,240 @@
+use std::fmt;
+
+use crate::{
+    bsp::Bsp,
+    header::{
+        MAX_MAP_CLIPNODES, MAX_MAP_EDGES, MAX_MAP_ENTITIES, MAX_MAP_ENTSTRING, MAX_MAP_FACES,
+        MAX_MAP_LEAFS, MAX_MAP_LIGHTING, MAX_MAP_MARKSURFACES, MAX_MAP_MIPTEX, MAX_MAP_MODELS,
+        MAX_MAP_NODES, MAX_MAP_PLANES, MAX_MAP_SURFEDGES, MAX_MAP_TEXINFO, MAX_MAP_TEXTURES,
+        MAX_MAP_VERTS, MAX_MAP_VISIBILITY,
+    },
+    writing::encoding::LumpEncoder,
+};
+
+/// # Engine limits
+///
+/// The `MAX_MAP_*` limits of an engine and its compilers. Counts are in
+/// elements, `entstring`, `miptex`, `lighting` and `visibility` in bytes.
+/// The profiles below cover the common engines; any field can be changed to
+/// match a custom build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LimitsProfile {
+    pub name: &'static str,
+    pub models: usize,
+    pub entities: usize,
+    pub entstring: usize,
+    pub planes: usize,
+    pub nodes: usize,
+    pub clipnodes: usize,
+    pub leafs: usize,
+    pub verts: usize,
+    pub faces: usize,
+    pub marksurfaces: usize,
+    pub texinfo: usize,
+    pub edges: usize,
+    pub surfedges: usize,
+    pub textures: usize,
+    pub miptex: usize,
+    pub lighting: usize,
+    pub visibility: usize,
+}
+
+impl LimitsProfile {
+    /// The limits of Half-Life and the original compilers, see `BspHeader`.
+    pub const GOLDSRC: LimitsProfile = LimitsProfile {
+        name: "GoldSrc",
+        models: MAX_MAP_MODELS.0,
+        entities: MAX_MAP_ENTITIES.0,
+        entstring: MAX_MAP_ENTSTRING.0,
+        planes: MAX_MAP_PLANES.0,
+        nodes: MAX_MAP_NODES.0,
+        clipnodes: MAX_MAP_CLIPNODES.0,
+        leafs: MAX_MAP_LEAFS.0,
+        verts: MAX_MAP_VERTS.0,
+        faces: MAX_MAP_FACES.0,
+        marksurfaces: MAX_MAP_MARKSURFACES.0,
+        texinfo: MAX_MAP_TEXINFO.0,
+        edges: MAX_MAP_EDGES.0,
+        surfedges: MAX_MAP_SURFEDGES.0,
+        textures: MAX_MAP_TEXTURES.0,
+        miptex: MAX_MAP_MIPTEX.0,
+        lighting: MAX_MAP_LIGHTING.0,
+        visibility: MAX_MAP_VISIBILITY.0,
+    };
+
+    /// Sven Co-op raises the limits that don't depend on the 16-bit indices
+    /// of the file format.
+    pub const SVEN_COOP: LimitsProfile = LimitsProfile {
+        name: "Sven Co-op",
+        models: 4096,
+        entities: 8192,
+        entstring: 2048 * 1024,
+        planes: 65535,
+        nodes: 32767,
+        clipnodes: 32767,
+        leafs: 32767,
+        verts: 65535,
+        faces: 65535,
+        marksurfaces: 65535,
+        texinfo: 32767,
+        edges: 256000,
+        surfedges: 512000,
+        textures: 4096,
+        miptex: 0x2000000,
+        lighting: 0x3000000,
+        visibility: 0x800000,
+    };
+
+    /// Xash3D with its extended limits enabled. The engine allows more of
+    /// the structures indexed with 16 bits than this, but only in its BSP2
+    /// format; a BSP30 map still can't go past its signed 16-bit node
+    /// children and unsigned 16-bit face, vertex and plane indices.
+    pub const XASH3D_EXTENDED: LimitsProfile = LimitsProfile {
+        name: "Xash3D (extended)",
+        models: 2048,
+        entities: 8192,
+        entstring: 0x200000,
+        planes: 65535,
+        nodes: 32767,
+        clipnodes: 32767,
+        leafs: 32767,
+        verts: 65535,
+        faces: 65535,
+        marksurfaces: 65535,
+        texinfo: 65535,
+        edges: 1048576,
+        surfedges: 2048000,
+        textures: 2048,
+        miptex: 0x2000000,
+        lighting: 0x2000000,
+        visibility: 0x1000000,
+    };
+}
+
+/// How much of one limit a map uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LimitUsage {
+    /// Name of the limit, as in the compilers' "exceeded" errors.
+    pub name: &'static str,
+    pub used: usize,
+    pub max: usize,
+}
+
+impl LimitUsage {
+    /// Used share of the limit, in percent. It goes past 100 when the limit
+    /// is exceeded.
+    pub fn utilisation(&self) -> f64 {
+        if self.max == 0 {
+            return if self.used == 0 { 0.0 } else { f64::INFINITY };
+        }
+        self.used as f64 * 100.0 / self.max as f64
+    }
+
+    pub fn is_exceeded(&self) -> bool {
+        self.used > self.max
+    }
+}
+
+impl fmt::Display for LimitUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<20} {:>9}/{:<9} ({:.1}%)",
+            self.name,
+            self.used,
+            self.max,
+            self.utilisation()
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LimitsReport {
+    pub profile: LimitsProfile,
+    pub usages: Vec<LimitUsage>,
+}
+
+impl LimitsReport {
+    pub fn get(&self, name: &str) -> Option<&LimitUsage> {
+        self.usages.iter().find(|usage| usage.name == name)
+    }
+
+    pub fn exceeded(&self) -> impl Iterator<Item = &LimitUsage> {
+        self.usages.iter().filter(|usage| usage.is_exceeded())
+    }
+
+    /// The limit the map is the closest to.
+    pub fn highest(&self) -> Option<&LimitUsage> {
+        self.usages
+            .iter()
+            .max_by(|a, b| a.utilisation().total_cmp(&b.utilisation()))
+    }
+}
+
+impl fmt::Display for LimitsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} limits:", self.profile.name)?;
+        for usage in &self.usages {
+            writeln!(f, "{usage}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Bsp {
+    /// # Limits report
+    ///
+    /// Compares the element counts and byte sizes of every lump against the
+    /// limits of the given engine. Byte sizes are those the lumps take once
+    /// written, so BSP29 lighting counts one byte per sample.
+    pub fn limits_report(&self, profile: &LimitsProfile) -> LimitsReport {
+        let version = self.version;
+        let usage = |name, used, max| LimitUsage { name, used, max };
+        LimitsReport {
+            profile: *profile,
+            usages: vec![
+                usage("MAX_MAP_MODELS", self.models.0.len(), profile.models),
+                usage("MAX_MAP_ENTITIES", self.entities.0.len(), profile.entities),
+                usage(
+                    "MAX_MAP_ENTSTRING",
+                    self.entities.text_len(),
+                    profile.entstring,
+                ),
+                usage("MAX_MAP_PLANES", self.planes.0.len(), profile.planes),
+                usage("MAX_MAP_NODES", self.nodes.0.len(), profile.nodes),
+                usage(
+                    "MAX_MAP_CLIPNODES",
+                    self.clip_nodes.0.len(),
+                    profile.clipnodes,
+                ),
+                usage("MAX_MAP_LEAFS", self.leaves.0.len(), profile.leafs),
+                usage("MAX_MAP_VERTS", self.vertices.0.len(), profile.verts),
+                usage("MAX_MAP_FACES", self.faces.0.len(), profile.faces),
+                usage(
+                    "MAX_MAP_MARKSURFACES",
+                    self.mark_surfaces.0.len(),
+                    profile.marksurfaces,
+                ),
+                usage("MAX_MAP_TEXINFO", self.tex_info.0.len(), profile.texinfo),
+                usage("MAX_MAP_EDGES", self.edges.0.len(), profile.edges),
+                usage(
+                    "MAX_MAP_SURFEDGES",
+                    self.surf_edges.0.len(),
+                    profile.surfedges,
+                ),
+                usage("MAX_MAP_TEXTURES", self.textures.0.len(), profile.textures),
+                usage(
+                    "MAX_MAP_MIPTEX",
+                    self.textures.encode(version).len(),
+                    profile.miptex,
+                ),
+                usage(
+                    "MAX_MAP_LIGHTING",
+                    self.light_map.encode(version).len(),
+                    profile.lighting,
+                ),
+                usage("MAX_MAP_VISIBILITY", self.vis.0.len(), profile.visibility),
+            ],
+        }
+    }
+}
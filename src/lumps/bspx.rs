@@ -0,0 +1,219 @@
+use std::ops::Index;
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::{math::Vector3D, parsing::decoding::BspParseError};
+
+use super::light_map::BspLightMapLump;
+
+pub const BSPX_MAGIC: [u8; 4] = *b"BSPX";
+pub const BSPX_LUMP_NAME: usize = 24;
+
+pub const BSPX_RGBLIGHTING: &str = "RGBLIGHTING";
+pub const BSPX_LMSHIFT: &str = "LMSHIFT";
+pub const BSPX_LMOFFSET: &str = "LMOFFSET";
+pub const BSPX_FACENORMALS: &str = "FACENORMALS";
+pub const BSPX_BRUSHLIST: &str = "BRUSHLIST";
+
+/// ```c
+/// typedef struct _BSPXHEADER {
+///     char szMagic[4]; // "BSPX"
+///     uint32_t nLumps;
+/// } BSPXHEADER;
+/// ```
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct BspxHeader {
+    pub sz_magic: [u8; 4],
+    pub n_lumps: i32,
+}
+
+/// ```c
+/// typedef struct _BSPXLUMP {
+///     char szName[24]; // null terminated
+///     uint32_t nOffset; // from the beginning of the file
+///     uint32_t nLength;
+/// } BSPXLUMP;
+/// ```
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct BspxLumpEntry {
+    pub sz_name: [u8; BSPX_LUMP_NAME],
+    pub n_offset: i32,
+    pub n_length: i32,
+}
+impl BspxLumpEntry {
+    pub fn name(&self) -> String {
+        let first = self
+            .sz_name
+            .iter()
+            .position(|&x| x == 0)
+            .unwrap_or(self.sz_name.len());
+        String::from_utf8_lossy(&self.sz_name[..first]).into_owned()
+    }
+}
+impl std::fmt::Debug for BspxLumpEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BspxLumpEntry")
+            .field("sz_name", &self.name())
+            .field("n_offset", &self.n_offset)
+            .field("n_length", &self.n_length)
+            .finish()
+    }
+}
+
+/// A named BSPX lump, kept as raw bytes.
+#[derive(Debug, Clone)]
+pub struct BspxLump {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// # BSPX
+///
+/// Extension lumps appended by modern Quake and GoldSrc compilers after the
+/// standard ones. The extension starts at the first 4 byte boundary past the
+/// end of the last standard lump with a small header, followed by a
+/// directory of named lumps:
+///
+/// - `RGBLIGHTING`: colored lighting for BSP29, laid out like the BSP30
+///   lighting lump.
+/// - `LMSHIFT`: one byte per face, the log2 of its lightmap scale.
+/// - `LMOFFSET`: one 32-bit offset per face into the lighting, replacing
+///   `BspFace::n_lightmap_offset` when `LMSHIFT` changes the lightmap sizes.
+/// - `FACENORMALS`: smoothed vertex normals, tangents and bitangents.
+/// - `BRUSHLIST`: the convex brushes of every model, used for collision.
+///
+/// The well-known lumps are decoded when the map is parsed, and every lump
+/// is kept as raw bytes as well, see `BspxLumps::get`. BSPX being optional,
+/// a lump that can't be read or decoded doesn't fail the map, it is only
+/// listed in `errors`.
+#[derive(Debug, Default)]
+pub struct BspxLumps {
+    /// Every lump that could be read, in directory order. Writing a map
+    /// writes these, the decoded lumps are not encoded back.
+    pub lumps: Vec<BspxLump>,
+    pub rgb_lighting: Option<BspLightMapLump>,
+    pub lm_shift: Option<BspxLmShift>,
+    pub lm_offset: Option<BspxLmOffset>,
+    pub face_normals: Option<BspxFaceNormals>,
+    pub brush_list: Option<BspxBrushList>,
+    pub errors: Vec<BspxLumpError>,
+}
+
+/// A BSPX lump that could not be read, or whose contents could not be
+/// decoded.
+#[derive(Debug)]
+pub struct BspxLumpError {
+    /// Name of the lump, empty when the directory itself is unreadable.
+    pub name: String,
+    pub error: BspParseError,
+}
+
+impl BspxLumps {
+    /// Raw bytes of the lump with the given name, compared case insensitively.
+    pub fn get(&self, name: &str) -> Option<&[u8]> {
+        self.lumps
+            .iter()
+            .find(|lump| lump.name.eq_ignore_ascii_case(name))
+            .map(|lump| lump.data.as_slice())
+    }
+}
+
+impl Index<usize> for BspxLumps {
+    type Output = BspxLump;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.lumps[index]
+    }
+}
+
+/// `LMSHIFT`: per face lightmap scale, as a power of two (4 is the default
+/// 16 units per luxel).
+#[derive(Debug, Clone)]
+pub struct BspxLmShift(pub Vec<u8>);
+
+/// `LMOFFSET`: per face offset into the lighting, -1 for unlit faces.
+#[derive(Debug, Clone)]
+pub struct BspxLmOffset(pub Vec<i32>);
+
+/// `FACENORMALS`:
+///
+/// ```c
+/// uint32_t nNormals;
+/// VECTOR3D normals[nNormals];
+/// // for every face, for every one of its vertices:
+/// uint32_t iNormal, iTangent, iBitangent;
+/// ```
+///
+/// `vertices` follow the faces lump in order, each face taking as many
+/// entries as it has edges.
+#[derive(Debug, Clone)]
+pub struct BspxFaceNormals {
+    pub normals: Vec<Vector3D>,
+    pub vertices: Vec<BspxVertexNormal>,
+}
+
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct BspxVertexNormal {
+    pub i_normal: u32,
+    pub i_tangent: u32,
+    pub i_bitangent: u32,
+}
+
+/// ```c
+/// typedef struct _BSPXBRUSHESMODEL {
+///     uint32_t nVersion; // 1
+///     uint32_t iModel;
+///     uint32_t nBrushes;
+///     uint32_t nPlanes;  // total over all the brushes
+/// } BSPXBRUSHESMODEL;
+/// ```
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct BspxBrushModelHeader {
+    pub n_version: u32,
+    pub i_model: u32,
+    pub n_brushes: u32,
+    pub n_planes: u32,
+}
+
+/// ```c
+/// typedef struct _BSPXBRUSH {
+///     float nMins[3], nMaxs[3];
+///     int16_t nContents;
+///     uint16_t nPlanes; // not counting the 6 axial ones of the bounds
+/// } BSPXBRUSH;
+/// ```
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct BspxBrushHeader {
+    pub n_mins: [f32; 3],
+    pub n_maxs: [f32; 3],
+    pub n_contents: i16,
+    pub n_planes: u16,
+}
+
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct BspxBrushPlane {
+    pub v_normal: Vector3D,
+    pub f_dist: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct BspxBrush {
+    pub header: BspxBrushHeader,
+    pub planes: Vec<BspxBrushPlane>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BspxModelBrushes {
+    pub header: BspxBrushModelHeader,
+    pub brushes: Vec<BspxBrush>,
+}
+
+/// `BRUSHLIST`: the brushes of every model that has any.
+#[derive(Debug, Clone)]
+pub struct BspxBrushList(pub Vec<BspxModelBrushes>);
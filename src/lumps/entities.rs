@@ -1,7 +1,339 @@
-use std::ops::Index;
+use std::{
+    borrow::Cow,
+    fmt,
+    ops::{Index, IndexMut},
+    str::FromStr,
+};
 
-#[derive(Debug)]
-pub struct BspEntity(pub Vec<(String, String)>);
+use crate::math::Vector3D;
+
+/// # Entity strings
+///
+/// A key or a value of an entity, kept as the exact bytes found between its
+/// quotes. The entity text has no declared encoding: maps made with recent
+/// editors are UTF-8, older ones often CP1252. `BspEntityString::to_str`
+/// reads the bytes as UTF-8 when they are valid, and as CP1252 otherwise.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct BspEntityString(pub Vec<u8>);
+
+/// Characters of the CP1252 bytes 0x80 to 0x9F. The 5 unassigned bytes map
+/// to the C1 control character of the same value, like ISO-8859-1.
+const CP1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+impl BspEntityString {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// The text of the string, decoded as UTF-8 if valid, as CP1252 if not.
+    pub fn to_str(&self) -> Cow<'_, str> {
+        match std::str::from_utf8(&self.0) {
+            Ok(text) => Cow::Borrowed(text),
+            Err(_) => Cow::Owned(
+                self.0
+                    .iter()
+                    .map(|&byte| match byte {
+                        0x80..=0x9F => CP1252_HIGH[byte as usize - 0x80],
+                        _ => byte as char,
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Length in bytes.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<&str> for BspEntityString {
+    fn from(value: &str) -> Self {
+        Self(value.as_bytes().to_vec())
+    }
+}
+
+impl From<String> for BspEntityString {
+    fn from(value: String) -> Self {
+        Self(value.into_bytes())
+    }
+}
+
+impl From<&[u8]> for BspEntityString {
+    fn from(value: &[u8]) -> Self {
+        Self(value.to_vec())
+    }
+}
+
+impl PartialEq<str> for BspEntityString {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl PartialEq<&str> for BspEntityString {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl fmt::Display for BspEntityString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_str())
+    }
+}
+
+impl fmt::Debug for BspEntityString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_str(), f)
+    }
+}
+
+/// The key/value pairs of an entity, in the order they appear in the text.
+#[derive(Debug, Clone, Default)]
+pub struct BspEntity(pub Vec<(BspEntityString, BspEntityString)>);
+
+/// A value of an entity that could not be read as the type its key holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BspEntityValueError {
+    pub key: String,
+    pub value: String,
+    /// What the value should have been, e.g. `3 numbers`.
+    pub expected: &'static str,
+}
+
+impl fmt::Display for BspEntityValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "bad value {:?} for {:?}, expected {}",
+            self.value, self.key, self.expected
+        )
+    }
+}
+
+impl std::error::Error for BspEntityValueError {}
+
+/// A pair of an entity that can't be written to the entity text, see
+/// `BspEntitiesLump::to_bytes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BspEntityWriteError {
+    /// A quote, a newline or a NUL in the key or the value of `key`, which
+    /// would end the string, the line or the lump.
+    BadCharacter {
+        entity: usize,
+        key: String,
+        byte: u8,
+    },
+    KeyLength {
+        entity: usize,
+        key: String,
+        found: usize,
+    },
+    ValueLength {
+        entity: usize,
+        key: String,
+        found: usize,
+    },
+}
+
+impl fmt::Display for BspEntityWriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadCharacter { entity, key, byte } => write!(
+                f,
+                "entity {entity} has a {:?} in its {key:?} pair, which can't be written",
+                *byte as char
+            ),
+            Self::KeyLength { entity, key, found } => write!(
+                f,
+                "entity {entity} has a {found} bytes key {key:?}, longer than the {MAX_KEY} allowed"
+            ),
+            Self::ValueLength { entity, key, found } => write!(
+                f,
+                "entity {entity} has a {found} bytes {key:?} value, longer than the {MAX_VALUE} allowed"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BspEntityWriteError {}
+
+impl BspEntity {
+    /// The pairs in their original order.
+    pub fn iter(&self) -> impl Iterator<Item = (&BspEntityString, &BspEntityString)> {
+        self.0.iter().map(|(key, value)| (key, value))
+    }
+
+    /// The value of `key`. When the key is repeated, the last value is the
+    /// one returned, as the engine applies pairs in order.
+    pub fn get(&self, key: &str) -> Option<&BspEntityString> {
+        self.0.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Every value of `key`, in order.
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a BspEntityString> {
+        self.0.iter().filter(move |(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Sets `key` to `value`. An existing key keeps its place, and when it
+    /// is repeated, the last value is the one replaced. Neither is checked
+    /// here, pairs the entity text can't hold fail when it is written.
+    pub fn set(&mut self, key: &str, value: impl Into<BspEntityString>) {
+        let value = value.into();
+        match self.0.iter_mut().rev().find(|(k, _)| k == key) {
+            Some((_, old)) => *old = value,
+            None => self.0.push((key.into(), value)),
+        }
+    }
+
+    /// Adds a pair at the end, even if the key is already set.
+    pub fn push(&mut self, key: impl Into<BspEntityString>, value: impl Into<BspEntityString>) {
+        self.0.push((key.into(), value.into()));
+    }
+
+    /// Removes every value of `key`, returning the one `get` would have.
+    pub fn remove(&mut self, key: &str) -> Option<BspEntityString> {
+        let mut removed = None;
+        self.0.retain(|(k, v)| {
+            if k != key {
+                return true;
+            }
+            removed = Some(v.clone());
+            false
+        });
+        removed
+    }
+
+    pub fn classname(&self) -> Option<Cow<'_, str>> {
+        self.get("classname").map(BspEntityString::to_str)
+    }
+
+    pub fn targetname(&self) -> Option<Cow<'_, str>> {
+        self.get("targetname").map(BspEntityString::to_str)
+    }
+
+    /// The `origin` key, the world origin when missing.
+    pub fn origin(&self) -> Result<Vector3D, BspEntityValueError> {
+        match self.get("origin") {
+            Some(value) => parse_vector("origin", value),
+            None => Ok(Vector3D {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            }),
+        }
+    }
+
+    /// # Entity angles
+    ///
+    /// The pitch, yaw and roll of the entity, in degrees. Besides `angles`,
+    /// the engine accepts a lone yaw in `angle`, which only replaces the yaw
+    /// of the `angles` set before it. A negative one replaces all three
+    /// instead, straight up when it truncates to -1, straight down otherwise:
+    ///
+    /// ```text
+    /// "angle" "90"                     => 0 90 0
+    /// "angles" "10 20 30" "angle" "45" => 10 45 30
+    /// "angle" "-1"                     => -90 0 0
+    /// "angle" "-2"                     => 90 0 0
+    /// "angle" "-90"                    => 90 0 0
+    /// ```
+    ///
+    /// The keys are applied in order, all zeros when neither is set.
+    pub fn angles(&self) -> Result<Vector3D, BspEntityValueError> {
+        let mut angles = Vector3D {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        for (key, value) in &self.0 {
+            if key == "angles" {
+                angles = parse_vector("angles", value)?;
+            } else if key == "angle" {
+                let yaw: f32 = parse_number("angle", value, "a number")?;
+                if yaw >= 0.0 {
+                    angles.y = yaw;
+                } else {
+                    let pitch = if yaw as i32 == -1 { -90.0 } else { 90.0 };
+                    angles = Vector3D {
+                        x: pitch,
+                        y: 0.0,
+                        z: 0.0,
+                    };
+                }
+            }
+        }
+        Ok(angles)
+    }
+
+    /// The `spawnflags` bits, 0 when missing.
+    pub fn spawnflags(&self) -> Result<u32, BspEntityValueError> {
+        self.get("spawnflags").map_or(Ok(0), |value| {
+            parse_number("spawnflags", value, "an unsigned integer")
+        })
+    }
+
+    /// The `rendercolor` as RGB, black when missing.
+    pub fn rendercolor(&self) -> Result<[u8; 3], BspEntityValueError> {
+        let Some(value) = self.get("rendercolor") else {
+            return Ok([0; 3]);
+        };
+        let components = parse_components::<u8>("rendercolor", value, "3 integers from 0 to 255")?;
+        Ok([components[0], components[1], components[2]])
+    }
+}
+
+fn value_error(key: &str, value: &BspEntityString, expected: &'static str) -> BspEntityValueError {
+    BspEntityValueError {
+        key: key.to_owned(),
+        value: value.to_string(),
+        expected,
+    }
+}
+
+fn parse_number<T: FromStr>(
+    key: &str,
+    value: &BspEntityString,
+    expected: &'static str,
+) -> Result<T, BspEntityValueError> {
+    value
+        .to_str()
+        .trim()
+        .parse()
+        .map_err(|_| value_error(key, value, expected))
+}
+
+/// Parses exactly 3 whitespace separated components.
+fn parse_components<T: FromStr>(
+    key: &str,
+    value: &BspEntityString,
+    expected: &'static str,
+) -> Result<[T; 3], BspEntityValueError> {
+    let text = value.to_str();
+    let components = text
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<Vec<T>, _>>()
+        .map_err(|_| value_error(key, value, expected))?;
+    components
+        .try_into()
+        .map_err(|_| value_error(key, value, expected))
+}
+
+fn parse_vector(key: &str, value: &BspEntityString) -> Result<Vector3D, BspEntityValueError> {
+    let [x, y, z] = parse_components(key, value, "3 numbers")?;
+    Ok(Vector3D { x, y, z })
+}
 
 pub const MAX_KEY: usize = 32;
 pub const MAX_VALUE: usize = 1024;
@@ -28,6 +360,11 @@ pub const MAX_VALUE: usize = 1024;
 /// entity specifying its type and therefore, how it is interpreted by the
 /// engine.
 ///
+/// The text is read like the engine does with `COM_Parse`: keys and values
+/// may be empty, `//` starts a comment running to the end of the line, and
+/// there are no escape sequences, a string runs until the next quote. The
+/// lump ends at its first NUL byte.
+///
 /// The map compilers also define two constants for the maximum length of key and
 /// value:
 ///
@@ -35,9 +372,62 @@ pub const MAX_VALUE: usize = 1024;
 /// #define MAX_KEY     32
 /// #define MAX_VALUE   1024
 /// ```
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct BspEntitiesLump(pub Vec<BspEntity>);
 
+impl<K: Into<BspEntityString>, V: Into<BspEntityString>> FromIterator<(K, V)> for BspEntity {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        Self(
+            iter.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
+
+impl BspEntitiesLump {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, BspEntity> {
+        self.0.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, BspEntity> {
+        self.0.iter_mut()
+    }
+
+    pub fn push(&mut self, entity: BspEntity) {
+        self.0.push(entity);
+    }
+
+    /// Inserts an entity at `index`, shifting the ones after it. The first
+    /// entity must stay the `worldspawn`.
+    pub fn insert(&mut self, index: usize, entity: BspEntity) {
+        self.0.insert(index, entity);
+    }
+
+    pub fn remove(&mut self, index: usize) -> BspEntity {
+        self.0.remove(index)
+    }
+
+    /// Keeps only the entities for which `keep` returns `true`.
+    pub fn retain(&mut self, keep: impl FnMut(&BspEntity) -> bool) {
+        self.0.retain(keep);
+    }
+}
+
+impl IndexMut<usize> for BspEntitiesLump {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.0[index]
+    }
+}
+
 impl Index<usize> for BspEntitiesLump {
     type Output = BspEntity;
 
@@ -1,3 +1,4 @@
+pub mod bspx;
 pub mod clip_nodes;
 pub mod entities;
 pub mod faces;
@@ -2,7 +2,7 @@ use std::ops::Index;
 
 use bytemuck::{Pod, Zeroable};
 
-///// Each node has exactly two children, which can be either another node or a leaf.
+/// Each node has exactly two children, which can be either another node or a leaf.
 /// A child node has two further children, and so on until all branches of the tree
 /// are terminated with leaves, which have no children. Each node also references
 /// a plane in the plane array.
@@ -23,27 +23,66 @@ use bytemuck::{Pod, Zeroable};
 #[derive(Debug, Clone, Copy, Pod, Zeroable)]
 #[repr(C)]
 pub struct BspNode {
-    pub plane_index: i32,
-    pub children_indices: [i32; 2],
+    pub plane_index: u32,
+    pub children_indices: [i16; 2],
     pub n_mins: [i16; 3],
     pub n_maxs: [i16; 3],
     pub first_face: u16,
     pub n_faces: u16,
 }
+impl BspNode {
+    /// Decodes both children of this node, front child first.
+    pub fn children(&self) -> [BspNodeChild; 2] {
+        [
+            BspNodeChild::from_raw(self.children_indices[0]),
+            BspNodeChild::from_raw(self.children_indices[1]),
+        ]
+    }
+}
+
+/// A decoded child reference of a `BspNode`.
+///
+/// On disk, non-negative values index the nodes lump, while negative values
+/// encode a leaf as `-(leaf + 1)`, which is the same as `!leaf`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BspNodeChild {
+    Node(usize),
+    Leaf(usize),
+}
+impl BspNodeChild {
+    pub fn from_raw(value: i16) -> Self {
+        if value >= 0 {
+            Self::Node(value as usize)
+        } else {
+            Self::Leaf(!value as usize)
+        }
+    }
+}
 
 /// # Nodes
 ///
 /// This lump is simple again and contains an array of binary structures, the
 /// nodes, which are a major part of the BSP tree.
 ///
+/// ```c
+/// typedef struct _BSPNODE {
+///     uint32_t iPlane;            // Index into Planes lump
+///     int16_t iChildren[2];       // If >= 0, then indices into Nodes
+///                                 // otherwise bitwise inverse indices into Leafs
+///     int16_t nMins[3], nMaxs[3]; // Defines bounding box
+///     uint16_t firstFace, nFaces; // Index and count into Faces
+/// } BSPNODE;
+/// ```
+///
 /// Every BSPNODE structure represents a node in the BSP tree and every node
 /// equals more or less a division step of the BSP algorithm. Therefore, each
 /// node has an index (iPlane) referring to a plane in the plane lump which
 /// divides the node into its two child nodes. The child nodes are also stored
 /// as indexes. Contrary to the plane index, the node index for the child is
-/// signed. If the index is larger than 0, the index indicates a child node. If
-/// it is equal to or smaller than zero (no valid array index), the bitwise
-/// inversed value of the index gives an index into the leaves lump.
+/// signed. If the index is larger than or equal to 0, the index indicates a
+/// child node. If it is smaller than zero (no valid array index), the bitwise
+/// inversed value of the index gives an index into the leaves lump. See
+/// `BspNodeChild` for the decoded form.
 /// Additionally, two points (nMins, nMaxs) span the bounding box (AABB, axis
 /// aligned bounding box) delimiting the space of the node. Finally, firstFace
 /// indexes into the face lump and specifies the first of nFaces surfaces
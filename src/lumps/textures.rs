@@ -17,8 +17,9 @@ pub struct BspTextureHeader {
 #[repr(C)]
 pub struct BspMipTexOffset(pub i32);
 
-const MAX_TEXTURE_NAME: usize = 16;
-const MIP_LEVELS: usize = 4;
+pub const MAX_TEXTURE_NAME: usize = 16;
+pub const MIP_LEVELS: usize = 4;
+pub const PALETTE_SIZE: usize = 256;
 
 #[derive(Clone, Copy, Pod, Zeroable)]
 #[repr(C)]
@@ -37,6 +38,20 @@ impl BspMipTex {
             .unwrap_or(self.sz_name.len());
         String::from_utf8_lossy(&self.sz_name[..first]).into_owned()
     }
+
+    /// A texture with no mip offsets has its pixels stored in a WAD file.
+    pub fn is_external(&self) -> bool {
+        self.n_offsets.iter().all(|&offset| offset == 0)
+    }
+
+    /// Width and height of the given mip level, each level halving the
+    /// previous one.
+    pub fn mip_size(&self, level: usize) -> (usize, usize) {
+        (
+            self.n_width.max(0) as usize >> level,
+            self.n_height.max(0) as usize >> level,
+        )
+    }
 }
 impl std::fmt::Debug for BspMipTex {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -92,13 +107,122 @@ impl std::fmt::Debug for BspMipTex {
 /// offsets at the end can either be zero if the texture is stored in an
 /// external WAD file, or point to the beginnings of the binary texture data
 /// within the texture lump relative to the beginning of its BSPMIPTEX struct.
+///
+/// Embedded textures store one byte per pixel, indexing into a palette that
+/// follows the last mip level. The palette is prefixed by the number of its
+/// colors (always 256) as a 16-bit integer, and each color is an RGB triple.
 #[derive(Debug)]
-pub struct BspTexturesLump(pub Vec<BspMipTex>);
+pub struct BspTexturesLump(pub Vec<BspTexture>);
 
 impl Index<usize> for BspTexturesLump {
-    type Output = BspMipTex;
+    type Output = BspTexture;
 
     fn index(&self, index: usize) -> &Self::Output {
         &self.0[index]
     }
 }
+
+/// The 256 RGB colors indexed by the pixels of a texture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BspPalette(pub Vec<[u8; 3]>);
+
+/// Decoded pixel data of a texture: the palette indices of every mip level
+/// and the palette they refer to. BSP29 textures have no palette of their
+/// own, they must be expanded with Quake's global one.
+#[derive(Debug, Clone)]
+pub struct BspMipTexPixels {
+    pub mips: [Vec<u8>; MIP_LEVELS],
+    pub palette: Option<BspPalette>,
+}
+impl BspMipTexPixels {
+    /// Expands the given mip level to RGBA8 using the texture's own palette,
+    /// if it has one.
+    pub fn rgba(&self, level: usize, masked: bool) -> Option<Vec<u8>> {
+        let palette = self.palette.as_ref()?;
+        self.rgba_with(level, masked, palette)
+    }
+
+    /// Expands the given mip level to RGBA8, or `None` past the last level.
+    /// When `masked` is set, palette index 255 becomes fully transparent, as
+    /// GoldSrc does for textures whose name starts with `{`.
+    pub fn rgba_with(&self, level: usize, masked: bool, palette: &BspPalette) -> Option<Vec<u8>> {
+        let mip = self.mips.get(level)?;
+        let mut out = Vec::with_capacity(mip.len() * 4);
+        for &index in mip {
+            if masked && index == 255 {
+                out.extend_from_slice(&[0, 0, 0, 0]);
+                continue;
+            }
+            let [r, g, b] = palette.0.get(index as usize).copied().unwrap_or_default();
+            out.extend_from_slice(&[r, g, b, 255]);
+        }
+        Some(out)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum BspTextureData {
+    /// The texture only names its pixels, which live in a WAD file.
+    External,
+    /// The offset table entry is -1, which compilers write when they could
+    /// not find a texture at all. The header of such textures is zeroed.
+    Missing,
+    Embedded(BspMipTexPixels),
+}
+
+/// A texture of the texture lump: its `BspMipTex` header and, if embedded in
+/// the BSP, its pixels.
+#[derive(Debug, Clone)]
+pub struct BspTexture {
+    pub mip_tex: BspMipTex,
+    pub data: BspTextureData,
+    /// Bytes found between the end of this texture and the next one, usually
+    /// the alignment padding of the WAD lump it was copied from. They are
+    /// kept so that writing the lump reproduces it.
+    pub padding: Vec<u8>,
+}
+impl BspTexture {
+    pub fn name(&self) -> String {
+        self.mip_tex.name()
+    }
+
+    /// Whether palette index 255 is see-through for this texture.
+    pub fn is_masked(&self) -> bool {
+        self.mip_tex.sz_name[0] == b'{'
+    }
+
+    pub fn pixels(&self) -> Option<&BspMipTexPixels> {
+        match &self.data {
+            BspTextureData::External | BspTextureData::Missing => None,
+            BspTextureData::Embedded(pixels) => Some(pixels),
+        }
+    }
+
+    /// RGBA8 pixels of the given mip level, or `None` for external textures,
+    /// for textures without a palette and past the last level.
+    pub fn rgba(&self, level: usize) -> Option<Vec<u8>> {
+        self.pixels()?.rgba(level, self.is_masked())
+    }
+
+    /// Size of the texture in the lump, from its header to the end of its
+    /// palette, without `padding`.
+    pub fn data_len(&self) -> usize {
+        let header = std::mem::size_of::<BspMipTex>();
+        match &self.data {
+            BspTextureData::Missing => 0,
+            BspTextureData::External => header,
+            BspTextureData::Embedded(pixels) => {
+                let mips = (0..MIP_LEVELS)
+                    .map(|level| {
+                        self.mip_tex.n_offsets[level].max(0) as usize + pixels.mips[level].len()
+                    })
+                    .fold(header, usize::max);
+                let palette = pixels
+                    .palette
+                    .as_ref()
+                    .map_or(0, |palette| 2 + palette.0.len() * 3);
+                mips + palette
+            }
+        }
+    }
+}
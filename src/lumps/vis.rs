@@ -1,7 +1,3 @@
-// TODO: This might need some work?
-#[derive(Debug)]
-pub struct BspVis;
-
 /// # VIS
 ///
 /// The VIS lump contains data, which is irrelevant to the actual BSP tree, but 
@@ -18,5 +14,122 @@ pub struct BspVis;
 /// > poorly optimized (several hours) and is also done by a separate compiler. It 
 /// > can therefore be skipped when compiling the map, resulting in BSP files with 
 /// > no VIS data at all!
+///
+/// The run-length encoding only compresses zeros: a zero byte is followed by
+/// the number of zero bytes it stands for, any other byte is copied as is.
+/// Each decompressed row holds one bit per VisLeaf, where bit `n` refers to
+/// leaf `n + 1`, as leaf 0 is the shared solid leaf and never visible.
 #[derive(Debug)]
-pub struct BspVisLump(pub Vec<BspVis>);
+pub struct BspVisLump(pub Vec<u8>);
+
+impl BspVisLump {
+    /// Decompresses the row starting at `offset` for a map with `n_vis_leafs`
+    /// VisLeaves. Truncated data leaves the remaining bits cleared.
+    pub fn decompress(&self, offset: usize, n_vis_leafs: usize) -> Vec<u8> {
+        let row_len = n_vis_leafs.div_ceil(8);
+        let mut row = Vec::with_capacity(row_len);
+        let mut input = self.0.iter().skip(offset);
+        while row.len() < row_len {
+            match input.next() {
+                Some(0) => {
+                    let count = input.next().copied().unwrap_or(0) as usize;
+                    let count = count.min(row_len - row.len());
+                    row.resize(row.len() + count, 0);
+                }
+                Some(&byte) => row.push(byte),
+                None => break,
+            }
+        }
+        row.resize(row_len, 0);
+        row
+    }
+}
+
+/// # Leaf set
+/// 
+/// A set of leaf indices, typically a decoded PVS.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LeafSet(Vec<u64>);
+
+impl LeafSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds the set from a decompressed VIS row, shifting every bit by one
+    /// so that the set is indexed by leaf.
+    pub fn from_vis_row(row: &[u8], n_vis_leafs: usize) -> Self {
+        let mut set = Self::new();
+        for leaf in 0..n_vis_leafs {
+            if row.get(leaf >> 3).is_some_and(|byte| byte & (1 << (leaf & 7)) != 0) {
+                set.insert(leaf + 1);
+            }
+        }
+        set
+    }
+
+    /// Every VisLeaf of a map with `n_vis_leafs` leaves.
+    pub fn all_visible(n_vis_leafs: usize) -> Self {
+        let mut set = Self::new();
+        for leaf in 1..=n_vis_leafs {
+            set.insert(leaf);
+        }
+        set
+    }
+
+    pub fn insert(&mut self, leaf: usize) {
+        let word = leaf / 64;
+        if self.0.len() <= word {
+            self.0.resize(word + 1, 0);
+        }
+        self.0[word] |= 1 << (leaf % 64);
+    }
+
+    pub fn contains(&self, leaf: usize) -> bool {
+        self.0
+            .get(leaf / 64)
+            .is_some_and(|word| word & (1 << (leaf % 64)) != 0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|&word| word == 0)
+    }
+
+    /// Iterates the leaf indices in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().enumerate().flat_map(|(i, &word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| i * 64 + bit)
+        })
+    }
+
+    pub fn union_with(&mut self, other: &LeafSet) {
+        if self.0.len() < other.0.len() {
+            self.0.resize(other.0.len(), 0);
+        }
+        for (word, other) in self.0.iter_mut().zip(&other.0) {
+            *word |= other;
+        }
+    }
+
+    pub fn union(&self, other: &LeafSet) -> LeafSet {
+        let mut set = self.clone();
+        set.union_with(other);
+        set
+    }
+}
+
+impl FromIterator<usize> for LeafSet {
+    fn from_iter<T: IntoIterator<Item = usize>>(iter: T) -> Self {
+        let mut set = Self::new();
+        for leaf in iter {
+            set.insert(leaf);
+        }
+        set
+    }
+}
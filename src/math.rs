@@ -7,3 +7,9 @@ pub struct Vector3D {
   pub y: f32,
   pub z: f32
 }
+
+impl Vector3D {
+  pub fn dot(&self, other: &Vector3D) -> f32 {
+    self.x * other.x + self.y * other.y + self.z * other.z
+  }
+}
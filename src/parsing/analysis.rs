@@ -0,0 +1,251 @@
+use std::fmt;
+
+use crate::{
+    header::{
+        BspHeader, BspLumpLayout, BspVersion, LumpType, HEADER_LUMPS, LUMP_ENTITIES, LUMP_PLANES,
+    },
+    lumps::{
+        clip_nodes::BspClipNodesLump,
+        entities::BspEntitiesLump,
+        faces::BspFacesLump,
+        leaves::BspLeavesLump,
+        light_map::BspLightMapLump,
+        models::BspModelsLump,
+        nodes::BspNodesLump,
+        planes::BspPlanesLump,
+        surfaces::{BspEdgesLump, BspMarkSurfacesLump, BspSurfEdgesLump},
+        tex_info::BspTexInfoLump,
+        textures::BspTexturesLump,
+        vertices::BspVerticesLump,
+        vis::BspVisLump,
+    },
+};
+
+use super::{
+    decoding::{BspLumpError, BspLumpErrorKind, BspParseError},
+    PtrLumpReader,
+};
+
+/// The order the Quake and GoldSrc compilers write the lumps in.
+pub const COMPILER_LUMP_ORDER: [usize; HEADER_LUMPS] =
+    [1, 10, 3, 5, 6, 7, 9, 11, 13, 12, 14, 8, 4, 0, 2];
+
+/// Size of the elements of each lump, 1 for lumps without fixed size
+/// elements.
+pub fn element_sizes(version: BspVersion) -> [usize; HEADER_LUMPS] {
+    [
+        BspEntitiesLump::ELEMENT_SIZE,
+        BspPlanesLump::ELEMENT_SIZE,
+        BspTexturesLump::ELEMENT_SIZE,
+        BspVerticesLump::ELEMENT_SIZE,
+        BspVisLump::ELEMENT_SIZE,
+        BspNodesLump::ELEMENT_SIZE,
+        BspTexInfoLump::ELEMENT_SIZE,
+        BspFacesLump::ELEMENT_SIZE,
+        if version.has_mono_lighting() {
+            1
+        } else {
+            BspLightMapLump::ELEMENT_SIZE
+        },
+        BspClipNodesLump::ELEMENT_SIZE,
+        BspLeavesLump::ELEMENT_SIZE,
+        BspMarkSurfacesLump::ELEMENT_SIZE,
+        BspEdgesLump::ELEMENT_SIZE,
+        BspSurfEdgesLump::ELEMENT_SIZE,
+        BspModelsLump::ELEMENT_SIZE,
+    ]
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderIssue {
+    /// The offset or length of the lump is negative.
+    NegativePointer { lump: LumpType },
+    /// The lump ends at `end`, past the end of the file.
+    PastEndOfFile { lump: LumpType, end: u64 },
+    /// The length of the lump is not a whole number of elements.
+    PartialElement { lump: LumpType, element_size: usize },
+    /// The lump starts inside the header.
+    OverlapsHeader { lump: LumpType },
+    /// Both lumps share some bytes.
+    Overlap { lump: LumpType, other: LumpType },
+    /// Unused bytes between two lumps, beyond the 4 byte alignment.
+    Gap { offset: u64, len: u64 },
+    /// The lump doesn't start on a 4 byte boundary.
+    Misaligned { lump: LumpType },
+    /// The lumps are neither in index order nor in the order the compilers
+    /// write them in.
+    UnusualOrder,
+}
+
+impl fmt::Display for HeaderIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NegativePointer { lump } => write!(f, "{lump} has a negative offset or length"),
+            Self::PastEndOfFile { lump, end } => {
+                write!(f, "{lump} ends past the end of the file, at {end}")
+            }
+            Self::PartialElement { lump, element_size } => write!(
+                f,
+                "{lump} length is not a multiple of its {element_size} byte elements"
+            ),
+            Self::OverlapsHeader { lump } => write!(f, "{lump} starts inside the header"),
+            Self::Overlap { lump, other } => write!(f, "{lump} overlaps the {other}"),
+            Self::Gap { offset, len } => write!(f, "{len} unused bytes at {offset}"),
+            Self::Misaligned { lump } => write!(f, "{lump} is not aligned to 4 bytes"),
+            Self::UnusualOrder => write!(f, "lumps are in an unusual order"),
+        }
+    }
+}
+
+/// The result of `BspHeader::analyze`. Errors make the lumps they concern
+/// unreadable, warnings are only unusual for a compiled map.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderAnalysis {
+    pub errors: Vec<HeaderIssue>,
+    pub warnings: Vec<HeaderIssue>,
+}
+
+impl HeaderAnalysis {
+    pub fn is_fatal(&self) -> bool {
+        !self.errors.is_empty()
+    }
+}
+
+impl BspHeader {
+    /// # Header analysis
+    ///
+    /// Checks the lump directory against the length of the file, without
+    /// reading any lump, so that malformed files can be rejected before
+    /// allocating anything for them. The parsers run it on every header.
+    ///
+    /// A raw Blue Shift header is recognized by its lengths alone: when the
+    /// plane lump isn't a whole number of planes but the entity lump is, both
+    /// pointers are swapped back before the checks, and the issues name the
+    /// lumps of the standard layout.
+    pub fn analyze(&self, file_len: u64) -> HeaderAnalysis {
+        self.with_layout(self.guess_layout())
+            .analyze_normalized(file_len)
+    }
+
+    /// Runs `BspHeader::analyze`, failing on its first error with the one
+    /// reading that lump would have given.
+    pub fn check(&self, file_len: u64) -> Result<HeaderAnalysis, BspParseError> {
+        let header = self.with_layout(self.guess_layout());
+        let analysis = header.analyze_normalized(file_len);
+        let sizes = element_sizes(self.version().unwrap_or(BspVersion::GoldSrc30));
+        let error = analysis.errors.iter().find_map(|issue| {
+            let (lump, kind) = match *issue {
+                HeaderIssue::NegativePointer { lump } => (lump, BspLumpErrorKind::NegativePointer),
+                HeaderIssue::PastEndOfFile { lump, .. } => (lump, BspLumpErrorKind::PastEndOfFile),
+                HeaderIssue::PartialElement { lump, .. } => {
+                    (lump, BspLumpErrorKind::PartialElement)
+                }
+                _ => return None,
+            };
+            Some(BspLumpError {
+                lump,
+                ptr: header.lump[lump.0],
+                element_size: sizes[lump.0],
+                file_len,
+                kind,
+            })
+        });
+        match error {
+            Some(error) => Err(BspParseError::Lump(error)),
+            None => Ok(analysis),
+        }
+    }
+
+    /// The layout the lump lengths point to, only telling Blue Shift headers
+    /// apart when the standard one can't be right.
+    fn guess_layout(&self) -> BspLumpLayout {
+        let plane_size = element_sizes(BspVersion::GoldSrc30)[LUMP_PLANES.0] as i32;
+        let whole = |lump: LumpType| {
+            let len = self.lump[lump.0].n_length;
+            len >= 0 && len % plane_size == 0
+        };
+        if self.version() == Some(BspVersion::GoldSrc30)
+            && !whole(LUMP_PLANES)
+            && whole(LUMP_ENTITIES)
+        {
+            BspLumpLayout::BlueShift
+        } else {
+            BspLumpLayout::Standard
+        }
+    }
+
+    fn analyze_normalized(&self, file_len: u64) -> HeaderAnalysis {
+        let mut analysis = HeaderAnalysis::default();
+        let sizes = element_sizes(self.version().unwrap_or(BspVersion::GoldSrc30));
+        let header_len = std::mem::size_of::<BspHeader>() as u64;
+        // (lump, start, end) of the lumps that hold any data.
+        let mut spans = vec![];
+        for (i, ptr) in self.lump.iter().enumerate() {
+            let lump = LumpType(i);
+            if ptr.n_offset < 0 || ptr.n_length < 0 {
+                analysis.errors.push(HeaderIssue::NegativePointer { lump });
+                continue;
+            }
+            let start = ptr.n_offset as u64;
+            let end = start + ptr.n_length as u64;
+            if end > file_len {
+                analysis
+                    .errors
+                    .push(HeaderIssue::PastEndOfFile { lump, end });
+            }
+            if !(ptr.n_length as usize).is_multiple_of(sizes[i]) {
+                analysis.errors.push(HeaderIssue::PartialElement {
+                    lump,
+                    element_size: sizes[i],
+                });
+            }
+            if ptr.n_length == 0 {
+                continue;
+            }
+            if start < header_len {
+                analysis.warnings.push(HeaderIssue::OverlapsHeader { lump });
+            }
+            if !start.is_multiple_of(4) {
+                analysis.warnings.push(HeaderIssue::Misaligned { lump });
+            }
+            spans.push((lump, start, end));
+        }
+
+        spans.sort_by_key(|&(lump, start, _)| (start, lump.0));
+        let mut position = header_len;
+        let mut last: Option<(LumpType, u64)> = None;
+        for &(lump, start, end) in &spans {
+            if let Some((other, other_end)) = last {
+                if start < other_end {
+                    analysis.warnings.push(HeaderIssue::Overlap { lump, other });
+                }
+            }
+            let aligned = position.next_multiple_of(4);
+            if start > aligned {
+                analysis.warnings.push(HeaderIssue::Gap {
+                    offset: position,
+                    len: start - position,
+                });
+            }
+            position = position.max(end);
+            if last.is_none_or(|(_, other_end)| end > other_end) {
+                last = Some((lump, end));
+            }
+        }
+
+        let order: Vec<usize> = spans.iter().map(|(lump, _, _)| lump.0).collect();
+        let follows = |expected: &[usize]| {
+            let expected: Vec<usize> = expected
+                .iter()
+                .copied()
+                .filter(|i| order.contains(i))
+                .collect();
+            expected == order
+        };
+        let index_order: Vec<usize> = (0..HEADER_LUMPS).collect();
+        if !follows(&index_order) && !follows(&COMPILER_LUMP_ORDER) {
+            analysis.warnings.push(HeaderIssue::UnusualOrder);
+        }
+        analysis
+    }
+}
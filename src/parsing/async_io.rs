@@ -0,0 +1,166 @@
+use std::io::{self, SeekFrom};
+
+use bytemuck::pod_read_unaligned;
+use futures::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+use crate::{
+    bsp::Bsp,
+    header::{BspHeader, BspVersion, LumpType, HEADER_LUMPS},
+    lumps::bspx::{BspxHeader, BspxLumpEntry, BSPX_MAGIC},
+};
+
+use super::{
+    bspx::bspx_offset, check_bspx_bytes, check_sizes, check_sizes_any_layout, decode_lumps,
+    stream::StreamedLumps, struct_at, BspLumpError, BspParseError, LumpExtractor, ParseOptions,
+    PtrLumpReader,
+};
+
+/// Reads `len` bytes at `offset`, failing with `UnexpectedEof` if the file
+/// is shorter.
+async fn read_at<R: AsyncRead + AsyncSeek + Unpin>(
+    read: &mut R,
+    offset: u64,
+    len: u64,
+) -> Result<Vec<u8>, BspParseError> {
+    read.seek(SeekFrom::Start(offset))
+        .await
+        .map_err(BspParseError::GenericError)?;
+    let mut buffer = vec![];
+    read.take(len)
+        .read_to_end(&mut buffer)
+        .await
+        .map_err(BspParseError::GenericError)?;
+    if (buffer.len() as u64) < len {
+        return Err(BspParseError::GenericError(
+            io::ErrorKind::UnexpectedEof.into(),
+        ));
+    }
+    Ok(buffer)
+}
+
+async fn file_len<R: AsyncSeek + Unpin>(read: &mut R) -> Result<u64, BspParseError> {
+    read.seek(SeekFrom::End(0))
+        .await
+        .map_err(BspParseError::GenericError)
+}
+
+/// Like `read_at`, reading only the part of the range that is in the file.
+async fn read_clamped<R: AsyncRead + AsyncSeek + Unpin>(
+    read: &mut R,
+    offset: u64,
+    len: u64,
+    file_len: u64,
+) -> Result<Vec<u8>, BspParseError> {
+    if offset >= file_len {
+        return Ok(vec![]);
+    }
+    read_at(read, offset, len.min(file_len - offset)).await
+}
+
+/// Reads the BSPX header, its directory, and the lumps it lists, for
+/// `Bsp::extract_bspx` to decode. The directory is checked against
+/// `options.max_total_bytes` before any lump is read. Ranges running past
+/// the end of the file are cut there, so that `Bsp::extract_bspx` reports
+/// them like for a file.
+async fn read_bspx<R: AsyncRead + AsyncSeek + Unpin>(
+    read: &mut R,
+    header: &BspHeader,
+    file_len: u64,
+    options: &ParseOptions,
+) -> Result<Vec<(u64, Vec<u8>)>, BspParseError> {
+    let offset = bspx_offset(header) as u64;
+    let header_len = std::mem::size_of::<BspxHeader>() as u64;
+    let buffer = read_clamped(read, offset, header_len, file_len).await?;
+    let bspx = (buffer.len() as u64 == header_len)
+        .then(|| struct_at::<BspxHeader>(&buffer, 0))
+        .transpose()?;
+    let mut lumps = vec![(offset, buffer)];
+    let Some(bspx) = bspx.filter(|bspx| bspx.sz_magic == BSPX_MAGIC) else {
+        return Ok(lumps);
+    };
+    let entry_len = std::mem::size_of::<BspxLumpEntry>();
+    let directory_offset = offset + header_len;
+    let directory_len = bspx.n_lumps.max(0) as u64 * entry_len as u64;
+    let directory = read_clamped(read, directory_offset, directory_len, file_len).await?;
+    if (directory.len() as u64) < directory_len {
+        lumps.push((directory_offset, directory));
+        return Ok(lumps);
+    }
+    let entries: Vec<BspxLumpEntry> = directory
+        .chunks_exact(entry_len)
+        .map(pod_read_unaligned)
+        .collect();
+    lumps.push((directory_offset, directory));
+    check_bspx_bytes(header, &entries, options)?;
+    for entry in entries {
+        let (Ok(offset), Ok(len)) = (u64::try_from(entry.n_offset), u64::try_from(entry.n_length))
+        else {
+            continue;
+        };
+        lumps.push((offset, read_clamped(read, offset, len, file_len).await?));
+    }
+    Ok(lumps)
+}
+
+impl BspHeader {
+    /// # Asynchronous lump extraction
+    ///
+    /// Like `BspHeader::extract_lump`, reading the bytes of the lump from an
+    /// async reader before decoding them.
+    pub async fn extract_lump_async<L, R>(&self, read: &mut R) -> Result<L, BspParseError>
+    where
+        L: PtrLumpReader,
+        BspHeader: LumpExtractor<L>,
+        R: AsyncRead + AsyncSeek + Unpin,
+    {
+        let ptr = LumpExtractor::<L>::get_pointer(self);
+        let file_len = file_len(read).await?;
+        BspLumpError::check(<Self as LumpExtractor<L>>::LUMP, ptr, 1, file_len)?;
+        let data = read_at(read, ptr.n_offset as u64, ptr.n_length as u64).await?;
+        self.extract_lump(&mut StreamedLumps::new(
+            vec![(ptr.n_offset as u64, data)],
+            file_len,
+        ))
+    }
+}
+
+impl Bsp {
+    /// # Asynchronous parsing
+    ///
+    /// Like `Bsp::parse`, over an async reader. The lumps are read without
+    /// blocking, then decoded like a synchronous read would.
+    pub async fn parse_async<R: AsyncRead + AsyncSeek + Unpin>(
+        read: &mut R,
+    ) -> Result<Box<Self>, BspParseError> {
+        Self::parse_async_with(read, &ParseOptions::unlimited()).await
+    }
+
+    /// Like `Bsp::parse_async`, with the budgets of `options`. The total size
+    /// and the element counts of the lumps are checked before any of them is
+    /// read.
+    pub async fn parse_async_with<R: AsyncRead + AsyncSeek + Unpin>(
+        read: &mut R,
+        options: &ParseOptions,
+    ) -> Result<Box<Self>, BspParseError> {
+        let file_len = file_len(read).await?;
+        let buffer = read_at(read, 0, std::mem::size_of::<BspHeader>() as u64).await?;
+        let header = Self::extract_header(&mut buffer.as_slice())?;
+        header.check(file_len)?;
+        check_sizes_any_layout(&header, options)?;
+        let mut lumps = Vec::with_capacity(HEADER_LUMPS + 1);
+        for (i, ptr) in header.lump.iter().enumerate() {
+            BspLumpError::check(LumpType(i), *ptr, 1, file_len)?;
+            let data = read_at(read, ptr.n_offset as u64, ptr.n_length as u64).await?;
+            lumps.push((ptr.n_offset as u64, data));
+        }
+        lumps.extend(read_bspx(read, &header, file_len, options).await?);
+        let mut lumps = StreamedLumps::new(lumps, file_len);
+        let layout = Self::detect_layout(&mut lumps, &header)?;
+        let header = header.with_layout(layout);
+        let version = header.version().unwrap_or(BspVersion::GoldSrc30);
+        check_sizes(&header, version, options)?;
+        decode_lumps(&mut lumps, header, layout, options, |read| {
+            Self::extract_bspx(read, &header, options)
+        })
+    }
+}
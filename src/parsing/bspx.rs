@@ -0,0 +1,197 @@
+use std::io::{self, Read, Seek};
+
+use bytemuck::try_cast_slice;
+
+use crate::{
+    bsp::Bsp,
+    header::{BspHeader, BspLumpPointer},
+    lumps::{
+        bspx::{
+            BspxBrush, BspxBrushHeader, BspxBrushList, BspxBrushModelHeader, BspxBrushPlane,
+            BspxFaceNormals, BspxHeader, BspxLmOffset, BspxLmShift, BspxLump, BspxLumpEntry,
+            BspxLumpError, BspxLumps, BspxModelBrushes, BspxVertexNormal, BSPX_BRUSHLIST,
+            BSPX_FACENORMALS, BSPX_LMOFFSET, BSPX_LMSHIFT, BSPX_MAGIC, BSPX_RGBLIGHTING,
+        },
+        light_map::{BspLightMap, BspLightMapLump},
+    },
+    math::Vector3D,
+};
+
+use super::{
+    check_bspx_bytes, options::ParseOptions, seek_and_extract, slice_at, struct_at, BspParseError,
+};
+
+/// Offset of the BSPX header: the end of the last standard lump, rounded up
+/// to 4 bytes.
+pub fn bspx_offset(header: &BspHeader) -> i64 {
+    let end = header
+        .lump
+        .iter()
+        .map(|ptr| ptr.n_offset as i64 + ptr.n_length as i64)
+        .max()
+        .unwrap_or(0)
+        .max(std::mem::size_of::<BspHeader>() as i64);
+    (end + 3) & !3
+}
+
+impl Bsp {
+    /// # BSPX extraction
+    ///
+    /// Reads the BSPX directory and all of its lumps, if the file has any.
+    /// Lumps that point out of the file are left out and listed in
+    /// `BspxLumps::errors`. The lengths in the directory are checked against
+    /// `options.max_total_bytes` before any lump is read.
+    pub fn extract_bspx<T: Seek + Read>(
+        read: &mut T,
+        header: &BspHeader,
+        options: &ParseOptions,
+    ) -> Result<Option<BspxLumps>, BspParseError> {
+        let ptr = BspLumpPointer {
+            n_offset: bspx_offset(header)
+                .try_into()
+                .map_err(BspParseError::BadPointerValue)?,
+            n_length: std::mem::size_of::<BspxHeader>() as i32,
+        };
+        let buffer = match seek_and_extract(read, &ptr) {
+            Ok(buffer) => buffer,
+            Err(BspParseError::GenericError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                return Ok(None)
+            }
+            Err(err) => return Err(err),
+        };
+        let bspx: BspxHeader = struct_at(&buffer, 0)?;
+        if bspx.sz_magic != BSPX_MAGIC {
+            return Ok(None);
+        }
+        let directory = BspLumpPointer {
+            n_offset: ptr.n_offset + ptr.n_length,
+            n_length: bspx
+                .n_lumps
+                .saturating_mul(std::mem::size_of::<BspxLumpEntry>() as i32),
+        };
+        let entries = match seek_and_extract(read, &directory).and_then(|buffer| {
+            let entries: &[BspxLumpEntry] =
+                try_cast_slice(&buffer).map_err(BspParseError::DeserializationError)?;
+            Ok(entries.to_vec())
+        }) {
+            Ok(entries) => entries,
+            Err(error) => {
+                return Ok(Some(BspxLumps::new(
+                    vec![],
+                    vec![BspxLumpError {
+                        name: String::new(),
+                        error,
+                    }],
+                )))
+            }
+        };
+        check_bspx_bytes(header, &entries, options)?;
+        let mut lumps = Vec::with_capacity(entries.len());
+        let mut errors = vec![];
+        for entry in &entries {
+            let ptr = BspLumpPointer {
+                n_offset: entry.n_offset,
+                n_length: entry.n_length,
+            };
+            match seek_and_extract(read, &ptr) {
+                Ok(data) => lumps.push(BspxLump {
+                    name: entry.name(),
+                    data,
+                }),
+                Err(error) => errors.push(BspxLumpError {
+                    name: entry.name(),
+                    error,
+                }),
+            }
+        }
+        Ok(Some(BspxLumps::new(lumps, errors)))
+    }
+}
+
+fn cast_lump<T: bytemuck::Pod>(data: &[u8]) -> Result<Vec<T>, BspParseError> {
+    let values: &[T] = try_cast_slice(data).map_err(BspParseError::DeserializationError)?;
+    Ok(values.to_owned())
+}
+
+impl BspxLumps {
+    /// Decodes the well-known lumps among `lumps`. The ones that fail to
+    /// decode are added to `errors`, and kept as raw bytes only.
+    pub fn new(lumps: Vec<BspxLump>, errors: Vec<BspxLumpError>) -> Self {
+        let mut bspx = Self {
+            lumps,
+            errors,
+            ..Self::default()
+        };
+        bspx.rgb_lighting = bspx.decode(BSPX_RGBLIGHTING, |data| {
+            cast_lump::<BspLightMap>(data).map(BspLightMapLump)
+        });
+        bspx.lm_shift = bspx.decode(BSPX_LMSHIFT, |data| Ok(BspxLmShift(data.to_vec())));
+        bspx.lm_offset = bspx.decode(BSPX_LMOFFSET, |data| {
+            data.chunks_exact(4)
+                .map(|chunk| struct_at(chunk, 0))
+                .collect::<Result<_, _>>()
+                .map(BspxLmOffset)
+        });
+        bspx.face_normals = bspx.decode(BSPX_FACENORMALS, decode_face_normals);
+        bspx.brush_list = bspx.decode(BSPX_BRUSHLIST, decode_brush_list);
+        bspx
+    }
+
+    fn decode<L>(
+        &mut self,
+        name: &str,
+        decode: impl FnOnce(&[u8]) -> Result<L, BspParseError>,
+    ) -> Option<L> {
+        match decode(self.get(name)?) {
+            Ok(lump) => Some(lump),
+            Err(error) => {
+                self.errors.push(BspxLumpError {
+                    name: name.to_owned(),
+                    error,
+                });
+                None
+            }
+        }
+    }
+}
+
+fn decode_face_normals(data: &[u8]) -> Result<BspxFaceNormals, BspParseError> {
+    let n_normals: u32 = struct_at(data, 0)?;
+    let normals_size = (n_normals as usize).saturating_mul(std::mem::size_of::<Vector3D>());
+    let normals = slice_at(data, 4, normals_size)?;
+    let vertices = &data[4 + normals_size..];
+    let vertices = &vertices[..vertices.len() - vertices.len() % 12];
+    Ok(BspxFaceNormals {
+        normals: (0..n_normals as usize)
+            .map(|i| struct_at(normals, i * 12))
+            .collect::<Result<_, _>>()?,
+        vertices: (0..vertices.len() / 12)
+            .map(|i| struct_at::<BspxVertexNormal>(vertices, i * 12))
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+fn decode_brush_list(data: &[u8]) -> Result<BspxBrushList, BspParseError> {
+    let mut models = vec![];
+    let mut offset = 0;
+    while offset < data.len() {
+        let header: BspxBrushModelHeader = struct_at(data, offset)?;
+        offset += std::mem::size_of::<BspxBrushModelHeader>();
+        let mut brushes = Vec::with_capacity((header.n_brushes as usize).min(data.len() / 28));
+        for _ in 0..header.n_brushes {
+            let brush: BspxBrushHeader = struct_at(data, offset)?;
+            offset += std::mem::size_of::<BspxBrushHeader>();
+            let mut planes = Vec::with_capacity(brush.n_planes as usize);
+            for _ in 0..brush.n_planes {
+                planes.push(struct_at::<BspxBrushPlane>(data, offset)?);
+                offset += std::mem::size_of::<BspxBrushPlane>();
+            }
+            brushes.push(BspxBrush {
+                header: brush,
+                planes,
+            });
+        }
+        models.push(BspxModelBrushes { header, brushes });
+    }
+    Ok(BspxBrushList(models))
+}
@@ -1,25 +1,219 @@
 use std::{
-    io::{self, Read, Seek},
+    fmt,
+    io::{self, Read, Seek, SeekFrom},
     num::TryFromIntError,
-    string::FromUtf8Error,
 };
 
-use bytemuck::{from_bytes, PodCastError};
-use peg::{error::ParseError, str::LineCol};
+use bytemuck::{from_bytes, pod_read_unaligned, PodCastError};
 
-use crate::header::BspLumpPointer;
+use crate::{
+    header::{BspLumpPointer, LumpType, LUMP_UNKNOWN},
+    lumps::{entities::BspEntityWriteError, textures::MIP_LEVELS},
+};
+
+use super::{entities::BspEntityParseError, options::ParseLimitError, stream::BackwardSeekError};
 
 #[derive(Debug)]
 pub enum BspParseError {
-    InvalidVersion { valid: Vec<i32>, found: i32 },
+    InvalidVersion {
+        valid: Vec<i32>,
+        found: i32,
+    },
+    InvalidMagic {
+        valid: [u8; 4],
+        found: [u8; 4],
+    },
+    UnsupportedCompression(u8),
     BadPointerValue(TryFromIntError),
-    BadStringValue(FromUtf8Error),
-    EntityLumpParseError(ParseError<LineCol>),
+    /// The mip offsets of a texture are neither all zero, for a texture of
+    /// a WAD file, nor all set, for an embedded one.
+    PartialMipTex([i32; MIP_LEVELS]),
+    EntityLumpParseError(BspEntityParseError),
+    /// The entities hold a pair the entity text can't, see
+    /// `BspEntitiesLump::to_bytes`.
+    EntityLumpWriteError(BspEntityWriteError),
     GenericError(io::Error),
     DeserializationError(PodCastError),
+    /// A lump of the header could not be read, see `BspLumpError`.
+    Lump(BspLumpError),
+    /// The map goes over a budget of `ParseOptions`.
+    LimitExceeded(ParseLimitError),
+    /// A streamed map points back into data that was already consumed.
+    BackwardSeek(BackwardSeekError),
+    /// An index asked for is past the last one, such as a texture or a mip
+    /// level.
+    IndexOutOfRange {
+        kind: &'static str,
+        index: usize,
+        len: usize,
+    },
+}
+
+impl fmt::Display for BspParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidVersion { valid, found } => {
+                write!(
+                    f,
+                    "unsupported BSP version {found}, expected one of {valid:?}"
+                )
+            }
+            Self::InvalidMagic { valid, found } => write!(
+                f,
+                "bad magic {:?}, expected {:?}",
+                String::from_utf8_lossy(found),
+                String::from_utf8_lossy(valid)
+            ),
+            Self::UnsupportedCompression(compression) => {
+                write!(f, "unsupported compression {compression}")
+            }
+            Self::BadPointerValue(err) => write!(f, "bad pointer value: {err}"),
+            Self::PartialMipTex(offsets) => {
+                write!(f, "mip offsets {offsets:?} are only partly set")
+            }
+            Self::EntityLumpParseError(err) => write!(f, "bad entity text: {err}"),
+            Self::EntityLumpWriteError(err) => write!(f, "{err}"),
+            Self::GenericError(err) => write!(f, "{err}"),
+            Self::DeserializationError(err) => write!(f, "bad lump data: {err:?}"),
+            Self::Lump(err) => write!(f, "{err}"),
+            Self::LimitExceeded(err) => write!(f, "{err}"),
+            Self::BackwardSeek(err) => write!(f, "{err}"),
+            Self::IndexOutOfRange { kind, index, len } => {
+                write!(f, "{kind} {index} is out of range, there are {len}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BspParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::BadPointerValue(err) => Some(err),
+            Self::EntityLumpParseError(err) => Some(err),
+            Self::EntityLumpWriteError(err) => Some(err),
+            Self::GenericError(err) => Some(err),
+            Self::Lump(err) => err.source(),
+            Self::BackwardSeek(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// # Lump errors
+///
+/// A failure to read one of the lumps of the header, along with what was
+/// known about it: its pointer, the size of its elements and the length of
+/// the whole file.
+#[derive(Debug)]
+pub struct BspLumpError {
+    pub lump: LumpType,
+    pub ptr: BspLumpPointer,
+    /// Size of one element of the lump, 1 for lumps without fixed size
+    /// elements.
+    pub element_size: usize,
+    pub file_len: u64,
+    pub kind: BspLumpErrorKind,
+}
+
+#[derive(Debug)]
+pub enum BspLumpErrorKind {
+    /// The offset or the length is negative.
+    NegativePointer,
+    /// The lump ends past the end of the file.
+    PastEndOfFile,
+    /// The length is not a whole number of elements.
+    PartialElement,
+    /// The lump is in bounds, but its contents could not be decoded.
+    Decoding(Box<BspParseError>),
+}
+
+impl fmt::Display for BspLumpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (offset {}, length {}, file length {}): ",
+            self.lump, self.ptr.n_offset, self.ptr.n_length, self.file_len
+        )?;
+        match &self.kind {
+            BspLumpErrorKind::NegativePointer => write!(f, "negative offset or length"),
+            BspLumpErrorKind::PastEndOfFile => write!(
+                f,
+                "ends {} bytes past the end of the file",
+                self.ptr.n_offset as i64 + self.ptr.n_length as i64 - self.file_len as i64
+            ),
+            BspLumpErrorKind::PartialElement => write!(
+                f,
+                "length is not a multiple of the {} byte element size",
+                self.element_size
+            ),
+            BspLumpErrorKind::Decoding(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for BspLumpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            BspLumpErrorKind::Decoding(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl BspLumpError {
+    /// Checks that the pointer of a lump lies within the file and holds a
+    /// whole number of elements.
+    pub fn check(
+        lump: LumpType,
+        ptr: BspLumpPointer,
+        element_size: usize,
+        file_len: u64,
+    ) -> Result<(), BspParseError> {
+        let kind = if ptr.n_offset < 0 || ptr.n_length < 0 {
+            BspLumpErrorKind::NegativePointer
+        } else if ptr.n_offset as u64 + ptr.n_length as u64 > file_len {
+            BspLumpErrorKind::PastEndOfFile
+        } else if !(ptr.n_length as usize).is_multiple_of(element_size.max(1)) {
+            BspLumpErrorKind::PartialElement
+        } else {
+            return Ok(());
+        };
+        Err(BspParseError::Lump(BspLumpError {
+            lump,
+            ptr,
+            element_size,
+            file_len,
+            kind,
+        }))
+    }
+
+    /// Wraps an error raised while decoding a lump, unless it already
+    /// carries a lump context.
+    pub fn wrap(
+        lump: LumpType,
+        ptr: BspLumpPointer,
+        element_size: usize,
+        file_len: u64,
+        err: BspParseError,
+    ) -> BspParseError {
+        match err {
+            BspParseError::Lump(_) => err,
+            err => BspParseError::Lump(BspLumpError {
+                lump,
+                ptr,
+                element_size,
+                file_len,
+                kind: BspLumpErrorKind::Decoding(Box::new(err)),
+            }),
+        }
+    }
 }
 
 pub trait PtrLumpReader {
+    /// Size of one element of the lump, 1 for lumps that aren't an array of
+    /// fixed size structs.
+    const ELEMENT_SIZE: usize = 1;
+
     fn read_from_ptr<T>(read: &mut T, ptr: &BspLumpPointer) -> Result<Self, BspParseError>
     where
         T: Seek + Read,
@@ -27,29 +221,60 @@ pub trait PtrLumpReader {
 }
 
 pub trait LumpExtractor<Lump: Sized + PtrLumpReader> {
+    /// The lump errors are reported against, `LUMP_UNKNOWN` for extractors
+    /// that don't tell.
+    const LUMP: LumpType = LUMP_UNKNOWN;
+
     fn get_pointer(&self) -> BspLumpPointer;
     fn extract_lump<T: Seek + Read>(&self, read: &mut T) -> Result<Lump, BspParseError> {
         let vis_ptr = self.get_pointer();
-        Lump::read_from_ptr(read, &vis_ptr)
+        extract_with_context(read, Self::LUMP, vis_ptr, Lump::ELEMENT_SIZE, |read| {
+            Lump::read_from_ptr(read, &vis_ptr)
+        })
     }
 }
 
+/// Checks a lump pointer against the length of the file, then decodes the
+/// lump with `decode`, attaching the lump context to any error.
+pub fn extract_with_context<T: Seek + Read, Lump>(
+    read: &mut T,
+    lump: LumpType,
+    ptr: BspLumpPointer,
+    element_size: usize,
+    decode: impl FnOnce(&mut T) -> Result<Lump, BspParseError>,
+) -> Result<Lump, BspParseError> {
+    let file_len = read
+        .seek(SeekFrom::End(0))
+        .map_err(BspParseError::GenericError)?;
+    BspLumpError::check(lump, ptr, element_size, file_len)?;
+    decode(read).map_err(|err| BspLumpError::wrap(lump, ptr, element_size, file_len, err))
+}
+
+/// Reads the bytes a pointer refers to. The buffer grows as data is read
+/// rather than being allocated upfront, so a bogus length fails with an
+/// `UnexpectedEof` instead of allocating it.
 pub fn seek_and_extract<T: Read + Seek>(
     read: &mut T,
     ptr: &BspLumpPointer,
 ) -> Result<Vec<u8>, BspParseError> {
     seek_ptr(read, ptr)?;
-    let mut buffer = vec![
-        0u8;
-        ptr.n_length
-            .try_into()
-            .map_err(BspParseError::BadPointerValue)?
-    ];
-    extract(read, &mut buffer)?;
+    let len: u64 = ptr
+        .n_length
+        .try_into()
+        .map_err(BspParseError::BadPointerValue)?;
+    let mut buffer = vec![];
+    read.take(len)
+        .read_to_end(&mut buffer)
+        .map_err(BspParseError::GenericError)?;
+    if (buffer.len() as u64) < len {
+        return Err(BspParseError::GenericError(
+            io::ErrorKind::UnexpectedEof.into(),
+        ));
+    }
     Ok(buffer)
 }
 
-pub fn extract(read: &mut dyn Read, buffer: &mut Vec<u8>) -> Result<(), BspParseError> {
+pub fn extract(read: &mut dyn Read, buffer: &mut [u8]) -> Result<(), BspParseError> {
     read.read_exact(buffer).map_err(BspParseError::GenericError)
 }
 
@@ -60,6 +285,21 @@ pub fn extract_struct<T: bytemuck::Pod>(read: &mut dyn Read) -> Result<T, BspPar
     Ok(result.to_owned())
 }
 
+/// Borrows `len` bytes at `offset` of an already extracted buffer, failing
+/// like a short read would if they are out of bounds.
+pub fn slice_at(data: &[u8], offset: usize, len: usize) -> Result<&[u8], BspParseError> {
+    offset
+        .checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or_else(|| BspParseError::GenericError(io::ErrorKind::UnexpectedEof.into()))
+}
+
+/// Reads a plain struct at `offset` of an already extracted buffer.
+pub fn struct_at<T: bytemuck::Pod>(data: &[u8], offset: usize) -> Result<T, BspParseError> {
+    let bytes = slice_at(data, offset, std::mem::size_of::<T>())?;
+    Ok(pod_read_unaligned(bytes))
+}
+
 pub fn seek_ptr(read: &mut dyn Seek, ptr: &BspLumpPointer) -> Result<u64, BspParseError> {
     read.seek(io::SeekFrom::Start(
         ptr.n_offset
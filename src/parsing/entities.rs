@@ -1,34 +1,236 @@
-use std::io::{Read, Seek};
+use std::{
+    fmt,
+    io::{Read, Seek},
+};
 
 use crate::{
-    header::{BspHeader, BspLumpPointer, LUMP_ENTITIES},
-    lumps::entities::{BspEntitiesLump, BspEntity},
+    header::{BspHeader, BspLumpPointer, LumpType, LUMP_ENTITIES},
+    lumps::entities::{BspEntitiesLump, BspEntity, BspEntityString},
 };
 
 use super::{seek_and_extract, BspParseError, LumpExtractor, PtrLumpReader};
 
-peg::parser! {
-  grammar entity_descriptor() for str {
-      pub rule entities() -> Vec<BspEntity>
-          = e:entity()+ _
-          { e }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BspEntityErrorKind {
+    /// Something else than `{` where an entity should start.
+    ExpectedOpenBrace,
+    /// The text ends in the middle of an entity.
+    UnexpectedEnd,
+    /// A key is followed by `}` instead of its value.
+    MissingValue,
+    /// A `{` where a key or a value should be, most likely the start of the
+    /// next entity when this one misses its `}`.
+    UnexpectedOpenBrace,
+}
+
+/// An error in the entity text, located by its byte offset in the lump and
+/// the 1-based line and column of that byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BspEntityParseError {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+    pub kind: BspEntityErrorKind,
+}
+
+impl BspEntityParseError {
+    fn new(text: &[u8], offset: usize, kind: BspEntityErrorKind) -> Self {
+        let before = &text[..offset.min(text.len())];
+        let line_start = before
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map_or(0, |i| i + 1);
+        Self {
+            offset,
+            line: before.iter().filter(|&&byte| byte == b'\n').count() + 1,
+            column: offset - line_start + 1,
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for BspEntityParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.kind {
+            BspEntityErrorKind::ExpectedOpenBrace => "expected `{`",
+            BspEntityErrorKind::UnexpectedEnd => "unexpected end of the entity text",
+            BspEntityErrorKind::MissingValue => "closing brace without data",
+            BspEntityErrorKind::UnexpectedOpenBrace => "`{` inside an entity",
+        };
+        write!(
+            f,
+            "{reason} at line {}, column {} (byte {})",
+            self.line, self.column, self.offset
+        )
+    }
+}
+
+impl std::error::Error for BspEntityParseError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+    Open,
+    Close,
+    Text(&'a [u8]),
+}
+
+/// Splits the entity text like the engine's `COM_Parse`: whitespace and
+/// `//` comments separate tokens, quoted strings run to the next quote with
+/// no escapes, and `{ } ( ) ' ,` are tokens of their own.
+struct Tokenizer<'a> {
+    text: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn is_single(byte: u8) -> bool {
+        matches!(byte, b'{' | b'}' | b'(' | b')' | b'\'' | b',')
+    }
 
-      pub rule entity() -> BspEntity
-          = _ "{" _ kv:kv_pairs() "}" _
-          { BspEntity(kv) }
+    fn skip_blank(&mut self) {
+        loop {
+            while self.text.get(self.position).is_some_and(|&c| c <= b' ') {
+                self.position += 1;
+            }
+            if self.text[self.position..].starts_with(b"//") {
+                while self.text.get(self.position).is_some_and(|&c| c != b'\n') {
+                    self.position += 1;
+                }
+                continue;
+            }
+            return;
+        }
+    }
 
-      rule kv_pairs() -> Vec<(String, String)>
-          = kv_pair()*
+    /// The next token and its offset.
+    fn next_token(&mut self) -> Option<(usize, Token<'a>)> {
+        self.skip_blank();
+        let start = self.position;
+        let &first = self.text.get(start)?;
+        if first == b'"' {
+            let end = self.text[start + 1..]
+                .iter()
+                .position(|&c| c == b'"')
+                .map_or(self.text.len(), |i| start + 1 + i);
+            self.position = (end + 1).min(self.text.len());
+            return Some((start, Token::Text(&self.text[start + 1..end])));
+        }
+        self.position += 1;
+        let token = match first {
+            b'{' => Token::Open,
+            b'}' => Token::Close,
+            _ if Self::is_single(first) => Token::Text(&self.text[start..start + 1]),
+            _ => {
+                while self
+                    .text
+                    .get(self.position)
+                    .is_some_and(|&c| c > b' ' && !Self::is_single(c))
+                {
+                    self.position += 1;
+                }
+                Token::Text(&self.text[start..self.position])
+            }
+        };
+        Some((start, token))
+    }
+}
 
-      rule kv_pair() -> (String, String)
-          =  "\"" key:string() "\"" _ "\"" value:string() "\"" _
-          { (key.into(), value.into()) }
+/// Parses one entity after its opening brace. A `{` in it fails with
+/// `UnexpectedOpenBrace` once consumed, so that the next entity can be
+/// parsed from there.
+fn parse_entity(tokens: &mut Tokenizer, text: &[u8]) -> Result<BspEntity, BspEntityParseError> {
+    let end = |tokens: &Tokenizer| {
+        BspEntityParseError::new(text, tokens.position, BspEntityErrorKind::UnexpectedEnd)
+    };
+    let error = |offset, kind| Err(BspEntityParseError::new(text, offset, kind));
+    let mut pairs = vec![];
+    loop {
+        let key = match tokens.next_token() {
+            None => return Err(end(tokens)),
+            Some((_, Token::Close)) => return Ok(BspEntity(pairs)),
+            Some((offset, Token::Open)) => {
+                return error(offset, BspEntityErrorKind::UnexpectedOpenBrace)
+            }
+            Some((_, Token::Text(key))) => key,
+        };
+        let value = match tokens.next_token() {
+            None => return Err(end(tokens)),
+            Some((offset, Token::Close)) => return error(offset, BspEntityErrorKind::MissingValue),
+            Some((offset, Token::Open)) => {
+                return error(offset, BspEntityErrorKind::UnexpectedOpenBrace)
+            }
+            Some((_, Token::Text(value))) => value,
+        };
+        pairs.push((BspEntityString::from(key), BspEntityString::from(value)));
+    }
+}
 
-      rule string() -> String
-          = value:$([^'"']+) { value.into() }
+/// Skips tokens up to and including the next `{`, returning whether there
+/// was one.
+fn skip_to_open(tokens: &mut Tokenizer) -> bool {
+    while let Some((_, token)) = tokens.next_token() {
+        if token == Token::Open {
+            return true;
+        }
+    }
+    false
+}
+
+fn parse_entities(
+    buffer: &[u8],
+    recover: bool,
+) -> Result<(BspEntitiesLump, Vec<BspEntityParseError>), BspEntityParseError> {
+    let text = &buffer[..buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len())];
+    let mut tokens = Tokenizer { text, position: 0 };
+    let mut entities = vec![];
+    let mut errors = vec![];
+    while let Some((offset, token)) = tokens.next_token() {
+        if token != Token::Open {
+            let err = BspEntityParseError::new(text, offset, BspEntityErrorKind::ExpectedOpenBrace);
+            if !recover {
+                return Err(err);
+            }
+            errors.push(err);
+            if !skip_to_open(&mut tokens) {
+                break;
+            }
+        }
+        loop {
+            match parse_entity(&mut tokens, text) {
+                Ok(entity) => entities.push(entity),
+                Err(err) if recover => {
+                    // The `{` of an unexpected brace is already consumed.
+                    let restart = err.kind == BspEntityErrorKind::UnexpectedOpenBrace;
+                    errors.push(err);
+                    if restart || skip_to_open(&mut tokens) {
+                        continue;
+                    }
+                }
+                Err(err) => return Err(err),
+            }
+            break;
+        }
+    }
+    Ok((BspEntitiesLump(entities), errors))
+}
 
-      rule _() = [' '|'\t'|'\r'|'\n']*
-  }
+/// Parses the text of the entity lump. The text ends at the first NUL byte,
+/// or at the end of the lump if there's none.
+pub fn decode_entities(buffer: &[u8]) -> Result<BspEntitiesLump, BspParseError> {
+    parse_entities(buffer, false)
+        .map(|(entities, _)| entities)
+        .map_err(BspParseError::EntityLumpParseError)
+}
+
+/// # Recovering entity parsing
+///
+/// Parses the entity text like `decode_entities`, but instead of failing on
+/// the first error, skips to the next `{` and carries on. An entity missing
+/// its `}` ends at the `{` of the next one. Entities with an error are
+/// dropped, every other one is kept, and the errors are returned along with
+/// them.
+pub fn decode_entities_recovering(buffer: &[u8]) -> (BspEntitiesLump, Vec<BspEntityParseError>) {
+    parse_entities(buffer, true).unwrap_or_default()
 }
 
 impl PtrLumpReader for BspEntitiesLump {
@@ -37,14 +239,12 @@ impl PtrLumpReader for BspEntitiesLump {
         T: Seek + Read,
     {
         let buffer = seek_and_extract(read, ptr)?;
-        let raw = String::from_utf8(buffer).map_err(BspParseError::BadStringValue)?;
-        let raw = &raw[0..raw.len() - 1];
-        let entities =
-            entity_descriptor::entities(raw).map_err(BspParseError::EntityLumpParseError)?;
-        Ok(BspEntitiesLump(entities))
+        decode_entities(&buffer)
     }
 }
 impl LumpExtractor<BspEntitiesLump> for BspHeader {
+    const LUMP: LumpType = LUMP_ENTITIES;
+
     fn get_pointer(&self) -> BspLumpPointer {
         self.lump[LUMP_ENTITIES.0]
     }
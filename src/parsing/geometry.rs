@@ -4,8 +4,8 @@ use bytemuck::try_cast_slice;
 
 use crate::{
     header::{
-        BspHeader, BspLumpPointer, LUMP_CLIPNODES, LUMP_EDGES, LUMP_FACES, LUMP_LEAVES,
-        LUMP_MARKSURFACES, LUMP_NODES, LUMP_PLANES, LUMP_SURFEDGES, LUMP_VERTICES,
+        BspHeader, BspLumpPointer, BspVersion, LumpType, LUMP_CLIPNODES, LUMP_EDGES, LUMP_FACES,
+        LUMP_LEAVES, LUMP_MARKSURFACES, LUMP_NODES, LUMP_PLANES, LUMP_SURFEDGES, LUMP_VERTICES,
     },
     lumps::{
         clip_nodes::{BspClipNode, BspClipNodesLump},
@@ -21,9 +21,11 @@ use crate::{
     },
 };
 
-use super::{seek_and_extract, BspParseError, LumpExtractor, PtrLumpReader};
+use super::{extract_with_context, seek_and_extract, BspParseError, LumpExtractor, PtrLumpReader};
 
 impl PtrLumpReader for BspPlanesLump {
+    const ELEMENT_SIZE: usize = std::mem::size_of::<BspPlane>();
+
     fn read_from_ptr<T>(read: &mut T, ptr: &BspLumpPointer) -> Result<Self, BspParseError>
     where
         T: Seek + Read,
@@ -36,12 +38,16 @@ impl PtrLumpReader for BspPlanesLump {
     }
 }
 impl LumpExtractor<BspPlanesLump> for BspHeader {
+    const LUMP: LumpType = LUMP_PLANES;
+
     fn get_pointer(&self) -> BspLumpPointer {
         self.lump[LUMP_PLANES.0]
     }
 }
 
 impl PtrLumpReader for BspVerticesLump {
+    const ELEMENT_SIZE: usize = std::mem::size_of::<BspVertex>();
+
     fn read_from_ptr<T>(read: &mut T, ptr: &BspLumpPointer) -> Result<Self, BspParseError>
     where
         T: Seek + Read,
@@ -54,12 +60,16 @@ impl PtrLumpReader for BspVerticesLump {
     }
 }
 impl LumpExtractor<BspVerticesLump> for BspHeader {
+    const LUMP: LumpType = LUMP_VERTICES;
+
     fn get_pointer(&self) -> BspLumpPointer {
         self.lump[LUMP_VERTICES.0]
     }
 }
 
 impl PtrLumpReader for BspNodesLump {
+    const ELEMENT_SIZE: usize = std::mem::size_of::<BspNode>();
+
     fn read_from_ptr<T>(read: &mut T, ptr: &BspLumpPointer) -> Result<Self, BspParseError>
     where
         T: Seek + Read,
@@ -72,12 +82,16 @@ impl PtrLumpReader for BspNodesLump {
     }
 }
 impl LumpExtractor<BspNodesLump> for BspHeader {
+    const LUMP: LumpType = LUMP_NODES;
+
     fn get_pointer(&self) -> BspLumpPointer {
         self.lump[LUMP_NODES.0]
     }
 }
 
 impl PtrLumpReader for BspFacesLump {
+    const ELEMENT_SIZE: usize = std::mem::size_of::<BspFace>();
+
     fn read_from_ptr<T>(read: &mut T, ptr: &BspLumpPointer) -> Result<Self, BspParseError>
     where
         T: Seek + Read,
@@ -90,12 +104,36 @@ impl PtrLumpReader for BspFacesLump {
     }
 }
 impl LumpExtractor<BspFacesLump> for BspHeader {
+    const LUMP: LumpType = LUMP_FACES;
+
     fn get_pointer(&self) -> BspLumpPointer {
         self.lump[LUMP_FACES.0]
     }
+
+    /// BSP29 lightmap offsets count samples, they are scaled to index the
+    /// RGB lighting the lump is expanded to.
+    fn extract_lump<T: Seek + Read>(&self, read: &mut T) -> Result<BspFacesLump, BspParseError> {
+        let ptr = LumpExtractor::<BspFacesLump>::get_pointer(self);
+        let mut faces =
+            extract_with_context(read, LUMP_FACES, ptr, BspFacesLump::ELEMENT_SIZE, |read| {
+                BspFacesLump::read_from_ptr(read, &ptr)
+            })?;
+        if self.version().is_some_and(BspVersion::has_mono_lighting) {
+            for face in faces
+                .0
+                .iter_mut()
+                .filter(|face| face.n_lightmap_offset >= 0)
+            {
+                face.n_lightmap_offset = face.n_lightmap_offset.saturating_mul(3);
+            }
+        }
+        Ok(faces)
+    }
 }
 
 impl PtrLumpReader for BspClipNodesLump {
+    const ELEMENT_SIZE: usize = std::mem::size_of::<BspClipNode>();
+
     fn read_from_ptr<T>(read: &mut T, ptr: &BspLumpPointer) -> Result<Self, BspParseError>
     where
         T: Seek + Read,
@@ -108,12 +146,16 @@ impl PtrLumpReader for BspClipNodesLump {
     }
 }
 impl LumpExtractor<BspClipNodesLump> for BspHeader {
+    const LUMP: LumpType = LUMP_CLIPNODES;
+
     fn get_pointer(&self) -> BspLumpPointer {
         self.lump[LUMP_CLIPNODES.0]
     }
 }
 
 impl PtrLumpReader for BspLeavesLump {
+    const ELEMENT_SIZE: usize = std::mem::size_of::<BspLeaf>();
+
     fn read_from_ptr<T>(read: &mut T, ptr: &BspLumpPointer) -> Result<Self, BspParseError>
     where
         T: Seek + Read,
@@ -126,12 +168,16 @@ impl PtrLumpReader for BspLeavesLump {
     }
 }
 impl LumpExtractor<BspLeavesLump> for BspHeader {
+    const LUMP: LumpType = LUMP_LEAVES;
+
     fn get_pointer(&self) -> BspLumpPointer {
         self.lump[LUMP_LEAVES.0]
     }
 }
 
 impl PtrLumpReader for BspMarkSurfacesLump {
+    const ELEMENT_SIZE: usize = std::mem::size_of::<BspMarkSurface>();
+
     fn read_from_ptr<T>(read: &mut T, ptr: &BspLumpPointer) -> Result<Self, BspParseError>
     where
         T: Seek + Read,
@@ -144,12 +190,16 @@ impl PtrLumpReader for BspMarkSurfacesLump {
     }
 }
 impl LumpExtractor<BspMarkSurfacesLump> for BspHeader {
+    const LUMP: LumpType = LUMP_MARKSURFACES;
+
     fn get_pointer(&self) -> BspLumpPointer {
         self.lump[LUMP_MARKSURFACES.0]
     }
 }
 
 impl PtrLumpReader for BspEdgesLump {
+    const ELEMENT_SIZE: usize = std::mem::size_of::<BspEdge>();
+
     fn read_from_ptr<T>(read: &mut T, ptr: &BspLumpPointer) -> Result<Self, BspParseError>
     where
         T: Seek + Read,
@@ -162,12 +212,16 @@ impl PtrLumpReader for BspEdgesLump {
     }
 }
 impl LumpExtractor<BspEdgesLump> for BspHeader {
+    const LUMP: LumpType = LUMP_EDGES;
+
     fn get_pointer(&self) -> BspLumpPointer {
         self.lump[LUMP_EDGES.0]
     }
 }
 
 impl PtrLumpReader for BspSurfEdgesLump {
+    const ELEMENT_SIZE: usize = std::mem::size_of::<BspSurfEdge>();
+
     fn read_from_ptr<T>(read: &mut T, ptr: &BspLumpPointer) -> Result<Self, BspParseError>
     where
         T: Seek + Read,
@@ -180,6 +234,8 @@ impl PtrLumpReader for BspSurfEdgesLump {
     }
 }
 impl LumpExtractor<BspSurfEdgesLump> for BspHeader {
+    const LUMP: LumpType = LUMP_SURFEDGES;
+
     fn get_pointer(&self) -> BspLumpPointer {
         self.lump[LUMP_SURFEDGES.0]
     }
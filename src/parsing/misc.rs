@@ -3,28 +3,40 @@ use std::io::{Read, Seek};
 use bytemuck::try_cast_slice;
 
 use crate::{
-    header::{BspHeader, BspLumpPointer, LUMP_LIGHTING, LUMP_MODELS, LUMP_VISIBILITY},
-    lumps::{light_map::{BspLightMap, BspLightMapLump}, models::{BspModel, BspModelsLump}, vis::BspVisLump},
+    header::{
+        BspHeader, BspLumpPointer, BspVersion, LumpType, LUMP_LIGHTING, LUMP_MODELS,
+        LUMP_VISIBILITY,
+    },
+    lumps::{
+        light_map::{BspLightMap, BspLightMapLump},
+        models::{BspModel, BspModelsLump},
+        vis::BspVisLump,
+    },
 };
 
-use super::{seek_and_extract, BspParseError, LumpExtractor, PtrLumpReader};
+use super::{extract_with_context, seek_and_extract, BspParseError, LumpExtractor, PtrLumpReader};
 
 impl PtrLumpReader for BspVisLump {
-    fn read_from_ptr<T>(_read: &mut T, _ptr: &BspLumpPointer) -> Result<Self, BspParseError>
+    fn read_from_ptr<T>(read: &mut T, ptr: &BspLumpPointer) -> Result<Self, BspParseError>
     where
         T: Seek + Read,
         Self: Sized,
     {
-        Ok(BspVisLump(vec![]))
+        let buffer = seek_and_extract(read, ptr)?;
+        Ok(BspVisLump(buffer))
     }
 }
 impl LumpExtractor<BspVisLump> for BspHeader {
+    const LUMP: LumpType = LUMP_VISIBILITY;
+
     fn get_pointer(&self) -> BspLumpPointer {
         self.lump[LUMP_VISIBILITY.0]
     }
 }
 
 impl PtrLumpReader for BspLightMapLump {
+    const ELEMENT_SIZE: usize = std::mem::size_of::<BspLightMap>();
+
     fn read_from_ptr<T>(read: &mut T, ptr: &BspLumpPointer) -> Result<Self, BspParseError>
     where
         T: Seek + Read,
@@ -37,12 +49,35 @@ impl PtrLumpReader for BspLightMapLump {
     }
 }
 impl LumpExtractor<BspLightMapLump> for BspHeader {
+    const LUMP: LumpType = LUMP_LIGHTING;
+
     fn get_pointer(&self) -> BspLumpPointer {
         self.lump[LUMP_LIGHTING.0]
     }
+
+    /// BSP29 lighting is expanded from one byte per sample to RGB.
+    fn extract_lump<T: Seek + Read>(&self, read: &mut T) -> Result<BspLightMapLump, BspParseError> {
+        let ptr = LumpExtractor::<BspLightMapLump>::get_pointer(self);
+        if !self.version().is_some_and(BspVersion::has_mono_lighting) {
+            return extract_with_context(read, LUMP_LIGHTING, ptr, 3, |read| {
+                BspLightMapLump::read_from_ptr(read, &ptr)
+            });
+        }
+        extract_with_context(read, LUMP_LIGHTING, ptr, 1, |read| {
+            let buffer = seek_and_extract(read, &ptr)?;
+            Ok(BspLightMapLump(
+                buffer
+                    .into_iter()
+                    .map(|value| BspLightMap(value, value, value))
+                    .collect(),
+            ))
+        })
+    }
 }
 
 impl PtrLumpReader for BspModelsLump {
+    const ELEMENT_SIZE: usize = std::mem::size_of::<BspModel>();
+
     fn read_from_ptr<T>(read: &mut T, ptr: &BspLumpPointer) -> Result<Self, BspParseError>
     where
         T: Seek + Read,
@@ -55,6 +90,8 @@ impl PtrLumpReader for BspModelsLump {
     }
 }
 impl LumpExtractor<BspModelsLump> for BspHeader {
+    const LUMP: LumpType = LUMP_MODELS;
+
     fn get_pointer(&self) -> BspLumpPointer {
         self.lump[LUMP_MODELS.0]
     }
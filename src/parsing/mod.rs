@@ -1,17 +1,26 @@
+pub mod analysis;
+#[cfg(feature = "async")]
+pub mod async_io;
+pub mod bspx;
 pub mod decoding;
 pub mod entities;
 pub mod geometry;
 pub mod misc;
+pub mod options;
+pub mod stream;
 pub mod textures;
 
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, SeekFrom};
 
 use bytemuck::from_bytes;
 
 use crate::{
     bsp::Bsp,
-    header::BspHeader,
+    header::{
+        BspHeader, BspLumpLayout, BspLumpPointer, BspVersion, LumpType, LUMP_ENTITIES, LUMP_PLANES,
+    },
     lumps::{
+        bspx::{BspxLumpEntry, BspxLumps},
         clip_nodes::BspClipNodesLump,
         entities::BspEntitiesLump,
         faces::BspFacesLump,
@@ -19,7 +28,7 @@ use crate::{
         light_map::BspLightMapLump,
         models::BspModelsLump,
         nodes::BspNodesLump,
-        planes::BspPlanesLump,
+        planes::{BspPlane, BspPlanesLump},
         surfaces::{BspEdgesLump, BspMarkSurfacesLump, BspSurfEdgesLump},
         tex_info::BspTexInfoLump,
         textures::BspTexturesLump,
@@ -27,60 +36,302 @@ use crate::{
         vis::BspVisLump,
     },
 };
+use analysis::element_sizes;
 use decoding::*;
+use entities::decode_entities_recovering;
+use options::{ParseLimitError, ParseOptions};
 
 impl Bsp {
-    /// Extracts an owned instance of the BSP header.
+    /// Extracts an owned instance of the BSP header, failing if its version
+    /// is not one of `BspVersion::SUPPORTED`.
     pub fn extract_header(read: &mut dyn Read) -> Result<BspHeader, BspParseError> {
         let mut buffer = vec![0u8; std::mem::size_of::<BspHeader>()];
         extract(read, &mut buffer)?;
         let data: &BspHeader = from_bytes(&buffer);
-        if data.n_version != 30 {
+        if data.version().is_none() {
             return Err(BspParseError::InvalidVersion {
-                valid: vec![30],
+                valid: BspVersion::SUPPORTED.map(BspVersion::raw).to_vec(),
                 found: data.n_version,
             });
         }
         Ok(data.clone())
     }
 
+    /// # Lump layout detection
+    ///
+    /// Tells standard BSP30 files apart from Blue Shift ones. The plane lump
+    /// must be a whole number of planes and the entity lump must be text
+    /// starting with `{`; when that only holds with both pointers swapped, the
+    /// file is a Blue Shift map. BSP29 files are always standard.
+    pub fn detect_layout<T: Seek + Read>(
+        read: &mut T,
+        header: &BspHeader,
+    ) -> Result<BspLumpLayout, BspParseError> {
+        if header.version() != Some(BspVersion::GoldSrc30) {
+            return Ok(BspLumpLayout::Standard);
+        }
+        let entities = header.lump[LUMP_ENTITIES.0];
+        let planes = header.lump[LUMP_PLANES.0];
+        if is_plane_lump(&planes) && is_entity_lump(read, &entities)? {
+            return Ok(BspLumpLayout::Standard);
+        }
+        if is_plane_lump(&entities) && is_entity_lump(read, &planes)? {
+            return Ok(BspLumpLayout::BlueShift);
+        }
+        Ok(BspLumpLayout::Standard)
+    }
+
+    /// Extracts the header and rearranges its lumps to the standard layout,
+    /// so that `BspHeader::extract_lump` works for Blue Shift maps too. Fails
+    /// if `BspHeader::analyze` finds a lump that can't be read.
+    pub fn extract_normalized_header<T: Seek + Read>(
+        read: &mut T,
+    ) -> Result<(BspHeader, BspLumpLayout), BspParseError> {
+        let header = Self::extract_header(read)?;
+        let layout = Self::detect_layout(read, &header)?;
+        let header = header.with_layout(layout);
+        let file_len = read
+            .seek(SeekFrom::End(0))
+            .map_err(BspParseError::GenericError)?;
+        header.check(file_len)?;
+        Ok((header, layout))
+    }
+
     /// # BSP Bulk Parsing
     ///
     /// Extracts the whole BSP to memory, you can extract granular data by using
     /// `BspHeader::extract_lump` and `Bsp::extract_header` manually.
     pub fn parse<T: Seek + Read>(read: &mut T) -> Result<Box<Self>, BspParseError> {
-        let header = Self::extract_header(read)?;
-        let entities: BspEntitiesLump = header.extract_lump(read)?;
-        let planes: BspPlanesLump = header.extract_lump(read)?;
-        let textures: BspTexturesLump = header.extract_lump(read)?;
-        let vertices: BspVerticesLump = header.extract_lump(read)?;
-        let vis: BspVisLump = header.extract_lump(read)?;
-        let nodes: BspNodesLump = header.extract_lump(read)?;
-        let tex_info: BspTexInfoLump = header.extract_lump(read)?;
-        let faces: BspFacesLump = header.extract_lump(read)?;
-        let light_map: BspLightMapLump = header.extract_lump(read)?;
-        let clip_nodes: BspClipNodesLump = header.extract_lump(read)?;
-        let leaves: BspLeavesLump = header.extract_lump(read)?;
-        let mark_surfaces: BspMarkSurfacesLump = header.extract_lump(read)?;
-        let edges: BspEdgesLump = header.extract_lump(read)?;
-        let surf_edges: BspSurfEdgesLump = header.extract_lump(read)?;
-        let models: BspModelsLump = header.extract_lump(read)?;
-        Ok(Box::new(Bsp {
-            entities,
-            planes,
-            textures,
-            vertices,
-            vis,
-            nodes,
-            tex_info,
-            faces,
-            light_map,
-            clip_nodes,
-            leaves,
-            mark_surfaces,
-            edges,
-            surf_edges,
-            models,
-        }))
+        Self::parse_with(read, &ParseOptions::unlimited())
+    }
+
+    /// Like `Bsp::parse`, failing with `BspParseError::LimitExceeded` as soon
+    /// as the map goes over one of the budgets of `options`.
+    pub fn parse_with<T: Seek + Read>(
+        read: &mut T,
+        options: &ParseOptions,
+    ) -> Result<Box<Self>, BspParseError> {
+        let (header, layout) = Self::extract_normalized_header(read)?;
+        let version = header.version().unwrap_or(BspVersion::GoldSrc30);
+        check_sizes(&header, version, options)?;
+        decode_lumps(read, header, layout, options, |read| {
+            Self::extract_bspx(read, &header, options)
+        })
+    }
+}
+
+/// Decodes every lump of a normalized header, the BSPX lumps being read by
+/// `bspx` once the standard ones are done.
+fn decode_lumps<T: Seek + Read>(
+    read: &mut T,
+    header: BspHeader,
+    layout: BspLumpLayout,
+    options: &ParseOptions,
+    bspx: impl FnOnce(&mut T) -> Result<Option<BspxLumps>, BspParseError>,
+) -> Result<Box<Bsp>, BspParseError> {
+    let version = header.version().unwrap_or(BspVersion::GoldSrc30);
+    let (entities, entity_diagnostics) = if options.recover_entities {
+        let ptr = LumpExtractor::<BspEntitiesLump>::get_pointer(&header);
+        extract_with_context(read, LUMP_ENTITIES, ptr, 1, |read| {
+            Ok(decode_entities_recovering(&seek_and_extract(read, &ptr)?))
+        })?
+    } else {
+        (header.extract_lump(read)?, vec![])
+    };
+    let planes: BspPlanesLump = header.extract_lump(read)?;
+    let textures: BspTexturesLump = header.extract_lump(read)?;
+    let vertices: BspVerticesLump = header.extract_lump(read)?;
+    let vis: BspVisLump = header.extract_lump(read)?;
+    let nodes: BspNodesLump = header.extract_lump(read)?;
+    let tex_info: BspTexInfoLump = header.extract_lump(read)?;
+    let faces: BspFacesLump = header.extract_lump(read)?;
+    let light_map: BspLightMapLump = header.extract_lump(read)?;
+    let clip_nodes: BspClipNodesLump = header.extract_lump(read)?;
+    let leaves: BspLeavesLump = header.extract_lump(read)?;
+    let mark_surfaces: BspMarkSurfacesLump = header.extract_lump(read)?;
+    let edges: BspEdgesLump = header.extract_lump(read)?;
+    let surf_edges: BspSurfEdgesLump = header.extract_lump(read)?;
+    let models: BspModelsLump = header.extract_lump(read)?;
+    let bspx = bspx(read)?;
+    check_entities(&entities, options)?;
+    Ok(Box::new(Bsp {
+        version,
+        layout,
+        header,
+        entities,
+        entity_diagnostics,
+        planes,
+        textures,
+        vertices,
+        vis,
+        nodes,
+        tex_info,
+        faces,
+        light_map,
+        clip_nodes,
+        leaves,
+        mark_surfaces,
+        edges,
+        surf_edges,
+        models,
+        bspx,
+    }))
+}
+
+fn lumps_len(header: &BspHeader) -> u64 {
+    header
+        .lump
+        .iter()
+        .map(|ptr| ptr.n_length.max(0) as u64)
+        .sum()
+}
+
+/// Like `check_total_bytes`, with the BSPX lumps listed in `entries` on top
+/// of the standard ones.
+fn check_bspx_bytes(
+    header: &BspHeader,
+    entries: &[BspxLumpEntry],
+    options: &ParseOptions,
+) -> Result<(), BspParseError> {
+    let found = entries
+        .iter()
+        .map(|entry| entry.n_length.max(0) as u64)
+        .fold(lumps_len(header), u64::saturating_add);
+    if found > options.max_total_bytes {
+        return Err(BspParseError::LimitExceeded(ParseLimitError::TotalBytes {
+            max: options.max_total_bytes,
+            found,
+        }));
+    }
+    Ok(())
+}
+
+/// Checks that a BSPX directory of `directory_len` bytes fits in what the
+/// standard lumps leave of `options.max_total_bytes`, before reading it from
+/// a stream whose length isn't known.
+fn check_bspx_directory(
+    header: &BspHeader,
+    directory_len: u64,
+    options: &ParseOptions,
+) -> Result<(), BspParseError> {
+    let found = lumps_len(header).saturating_add(directory_len);
+    if found > options.max_total_bytes {
+        return Err(BspParseError::LimitExceeded(ParseLimitError::TotalBytes {
+            max: options.max_total_bytes,
+            found,
+        }));
+    }
+    Ok(())
+}
+
+fn check_total_bytes(header: &BspHeader, options: &ParseOptions) -> Result<(), BspParseError> {
+    let found = lumps_len(header);
+    if found > options.max_total_bytes {
+        return Err(BspParseError::LimitExceeded(ParseLimitError::TotalBytes {
+            max: options.max_total_bytes,
+            found,
+        }));
+    }
+    Ok(())
+}
+
+fn check_sizes(
+    header: &BspHeader,
+    version: BspVersion,
+    options: &ParseOptions,
+) -> Result<(), BspParseError> {
+    check_total_bytes(header, options)?;
+    let sizes = element_sizes(version);
+    for (i, ptr) in header.lump.iter().enumerate() {
+        let found = ptr.n_length.max(0) as usize / sizes[i];
+        if found > options.max_elements[i] {
+            return Err(BspParseError::LimitExceeded(ParseLimitError::Elements {
+                lump: LumpType(i),
+                max: options.max_elements[i],
+                found,
+            }));
+        }
+    }
+    Ok(())
+}
+
+/// `check_sizes` for a header read before its layout could be detected,
+/// failing only when the map is over budget in every layout its plane lump
+/// allows. The check has to be repeated once the header is normalized.
+fn check_sizes_any_layout(header: &BspHeader, options: &ParseOptions) -> Result<(), BspParseError> {
+    let version = header.version().unwrap_or(BspVersion::GoldSrc30);
+    let mut layouts = vec![];
+    if is_plane_lump(&header.lump[LUMP_PLANES.0]) {
+        layouts.push(BspLumpLayout::Standard);
+    }
+    if version == BspVersion::GoldSrc30 && is_plane_lump(&header.lump[LUMP_ENTITIES.0]) {
+        layouts.push(BspLumpLayout::BlueShift);
+    }
+    if layouts.is_empty() {
+        layouts.push(BspLumpLayout::Standard);
+    }
+    let mut first_error = None;
+    for layout in layouts {
+        match check_sizes(&header.with_layout(layout), version, options) {
+            Ok(()) => return Ok(()),
+            Err(err) => {
+                first_error.get_or_insert(err);
+            }
+        }
+    }
+    Err(first_error.unwrap())
+}
+
+fn check_entities(entities: &BspEntitiesLump, options: &ParseOptions) -> Result<(), BspParseError> {
+    if entities.0.len() > options.max_entities {
+        return Err(BspParseError::LimitExceeded(ParseLimitError::Entities {
+            max: options.max_entities,
+            found: entities.0.len(),
+        }));
+    }
+    for (entity_index, entity) in entities.0.iter().enumerate() {
+        for (key, value) in &entity.0 {
+            if key.len() > options.max_key_len {
+                return Err(BspParseError::LimitExceeded(ParseLimitError::KeyLength {
+                    entity: entity_index,
+                    max: options.max_key_len,
+                    found: key.len(),
+                }));
+            }
+            if value.len() > options.max_value_len {
+                return Err(BspParseError::LimitExceeded(ParseLimitError::ValueLength {
+                    entity: entity_index,
+                    key: key.to_string(),
+                    max: options.max_value_len,
+                    found: value.len(),
+                }));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_plane_lump(ptr: &BspLumpPointer) -> bool {
+    ptr.n_length >= 0 && (ptr.n_length as usize).is_multiple_of(std::mem::size_of::<BspPlane>())
+}
+
+fn is_entity_lump<T: Seek + Read>(
+    read: &mut T,
+    ptr: &BspLumpPointer,
+) -> Result<bool, BspParseError> {
+    const SAMPLE: i32 = 64;
+    if ptr.n_length <= 0 || ptr.n_offset < 0 {
+        return Ok(false);
     }
+    let sample = BspLumpPointer {
+        n_offset: ptr.n_offset,
+        n_length: ptr.n_length.min(SAMPLE),
+    };
+    let Ok(buffer) = seek_and_extract(read, &sample) else {
+        return Ok(false);
+    };
+    Ok(buffer
+        .iter()
+        .find(|byte| !byte.is_ascii_whitespace())
+        .is_some_and(|&byte| byte == b'{'))
 }
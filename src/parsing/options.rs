@@ -0,0 +1,121 @@
+use std::fmt;
+
+use crate::{
+    header::{LumpType, HEADER_LUMPS},
+    lumps::entities::{MAX_KEY, MAX_VALUE},
+};
+
+/// # Parse options
+///
+/// Resource budgets for `Bsp::parse_with`, for maps that can't be trusted.
+/// The sizes are checked against the header before any lump is read, and
+/// against the BSPX directory before any BSPX lump is. The entity limits,
+/// keys and values included, are only checked once the whole entity lump is
+/// parsed: bound its size with `max_elements` to bound that work.
+///
+/// `LazyBsp` and `BspView` take no options, they only read the lumps they
+/// are asked for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Maximum sum of the lengths of all the lumps, BSPX ones included.
+    pub max_total_bytes: u64,
+    /// Maximum number of elements of each lump, by lump index. Lumps without
+    /// fixed size elements, like entities, textures, and visibility, count
+    /// bytes.
+    pub max_elements: [usize; HEADER_LUMPS],
+    pub max_entities: usize,
+    /// Maximum length of a key, in bytes.
+    pub max_key_len: usize,
+    /// Maximum length of a value, in bytes.
+    pub max_value_len: usize,
+    /// Keep going after errors in the entity text, see
+    /// `decode_entities_recovering`. The errors end up in
+    /// `Bsp::entity_diagnostics`.
+    pub recover_entities: bool,
+}
+
+impl ParseOptions {
+    /// No limits at all, what `Bsp::parse` uses.
+    pub fn unlimited() -> Self {
+        Self {
+            max_total_bytes: u64::MAX,
+            max_elements: [usize::MAX; HEADER_LUMPS],
+            max_entities: usize::MAX,
+            max_key_len: usize::MAX,
+            max_value_len: usize::MAX,
+            recover_entities: false,
+        }
+    }
+}
+
+/// Only limits keys and values, to the compilers' `MAX_KEY` and `MAX_VALUE`.
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            max_key_len: MAX_KEY,
+            max_value_len: MAX_VALUE,
+            ..Self::unlimited()
+        }
+    }
+}
+
+/// A budget of `ParseOptions` that a map goes over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseLimitError {
+    TotalBytes {
+        max: u64,
+        found: u64,
+    },
+    Elements {
+        lump: LumpType,
+        max: usize,
+        found: usize,
+    },
+    Entities {
+        max: usize,
+        found: usize,
+    },
+    KeyLength {
+        entity: usize,
+        max: usize,
+        found: usize,
+    },
+    ValueLength {
+        entity: usize,
+        key: String,
+        max: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for ParseLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TotalBytes { max, found } => {
+                write!(f, "lumps take {found} bytes, more than the {max} allowed")
+            }
+            Self::Elements { lump, max, found } => {
+                write!(
+                    f,
+                    "{lump} has {found} elements, more than the {max} allowed"
+                )
+            }
+            Self::Entities { max, found } => {
+                write!(f, "{found} entities, more than the {max} allowed")
+            }
+            Self::KeyLength { entity, max, found } => write!(
+                f,
+                "entity {entity} has a {found} bytes key, longer than the {max} allowed"
+            ),
+            Self::ValueLength {
+                entity,
+                key,
+                max,
+                found,
+            } => write!(
+                f,
+                "entity {entity} has a {found} bytes \"{key}\" value, longer than the {max} allowed"
+            ),
+        }
+    }
+}
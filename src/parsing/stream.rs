@@ -0,0 +1,291 @@
+use std::{
+    fmt,
+    io::{self, Read, Seek, SeekFrom},
+};
+
+use bytemuck::try_cast_slice;
+
+use crate::{
+    bsp::Bsp,
+    header::{BspHeader, BspVersion, LumpType, HEADER_LUMPS},
+    lumps::bspx::{BspxHeader, BspxLump, BspxLumpEntry, BspxLumpError, BspxLumps, BSPX_MAGIC},
+};
+
+use super::{
+    bspx::bspx_offset, check_bspx_bytes, check_bspx_directory, check_sizes, check_sizes_any_layout,
+    decode_lumps, struct_at, BspLumpError, BspLumpErrorKind, BspParseError, ParseOptions,
+};
+
+/// A pointer of a streamed file that lies before data which was already
+/// consumed, reaching it would need to seek backwards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackwardSeekError {
+    /// What the pointer refers to, e.g. `planes lump` or `BSPX lump LMSHIFT`.
+    pub name: String,
+    pub offset: u64,
+    /// How many bytes of the stream were consumed when the pointer was met.
+    pub position: u64,
+}
+
+impl fmt::Display for BackwardSeekError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at offset {} can't be streamed, the stream is already at offset {}",
+            self.name, self.offset, self.position
+        )
+    }
+}
+
+impl std::error::Error for BackwardSeekError {}
+
+/// A `Read` that only ever moves forward, keeping track of its position.
+struct ForwardReader<'a, R: Read> {
+    inner: &'a mut R,
+    position: u64,
+}
+
+impl<R: Read> ForwardReader<'_, R> {
+    /// Discards bytes up to `offset`, returning `false` if the stream ends
+    /// before it.
+    fn skip_to(&mut self, offset: u64, name: impl Fn() -> String) -> Result<bool, BspParseError> {
+        if offset < self.position {
+            return Err(BspParseError::BackwardSeek(BackwardSeekError {
+                name: name(),
+                offset,
+                position: self.position,
+            }));
+        }
+        let len = offset - self.position;
+        let skipped = io::copy(&mut self.inner.take(len), &mut io::sink())
+            .map_err(BspParseError::GenericError)?;
+        self.position += skipped;
+        Ok(skipped == len)
+    }
+
+    /// Reads up to `len` bytes, less only if the stream ends first.
+    fn read_up_to(&mut self, len: u64) -> Result<Vec<u8>, BspParseError> {
+        let mut buffer = vec![];
+        self.inner
+            .take(len)
+            .read_to_end(&mut buffer)
+            .map_err(BspParseError::GenericError)?;
+        self.position += buffer.len() as u64;
+        Ok(buffer)
+    }
+}
+
+/// The lumps read from a stream, kept at their offsets so that they can be
+/// decoded like a file. Reading outside of them fails.
+pub(super) struct StreamedLumps {
+    lumps: Vec<(u64, Vec<u8>)>,
+    len: u64,
+    position: u64,
+}
+
+impl StreamedLumps {
+    /// `lumps` are the offsets and bytes that were read of a file of `len`
+    /// bytes.
+    pub(super) fn new(lumps: Vec<(u64, Vec<u8>)>, len: u64) -> Self {
+        Self {
+            lumps,
+            len,
+            position: 0,
+        }
+    }
+}
+
+impl Read for StreamedLumps {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.len {
+            return Ok(0);
+        }
+        let Some((offset, data)) = self
+            .lumps
+            .iter()
+            .find(|(offset, data)| (*offset..*offset + data.len() as u64).contains(&self.position))
+        else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("offset {} was not streamed", self.position),
+            ));
+        };
+        let available = &data[(self.position - offset) as usize..];
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for StreamedLumps {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        };
+        self.position = position.ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+        Ok(self.position)
+    }
+}
+
+impl Bsp {
+    /// # Streaming parsing
+    ///
+    /// Parses a map from a reader that can't seek, such as a socket or a
+    /// decompressor, in a single forward pass: the lumps are read in the
+    /// order of their offsets and the gaps between them are skipped. Files
+    /// whose lumps overlap, or point into the header, would need to seek
+    /// backwards and fail with `BspParseError::BackwardSeek`.
+    pub fn parse_stream<R: Read>(read: &mut R) -> Result<Box<Self>, BspParseError> {
+        Self::parse_stream_with(read, &ParseOptions::unlimited())
+    }
+
+    /// Like `Bsp::parse_stream`, with the budgets of `options`. The total
+    /// size and the element counts of the lumps are checked before any of
+    /// them is read.
+    pub fn parse_stream_with<R: Read>(
+        read: &mut R,
+        options: &ParseOptions,
+    ) -> Result<Box<Self>, BspParseError> {
+        let header = Self::extract_header(read)?;
+        // The length of a stream isn't known, the lumps past its end are
+        // caught while reading them.
+        header.check(u64::MAX)?;
+        check_sizes_any_layout(&header, options)?;
+        let mut stream = ForwardReader {
+            inner: read,
+            position: std::mem::size_of::<BspHeader>() as u64,
+        };
+        let mut order: Vec<usize> = (0..HEADER_LUMPS).collect();
+        order.sort_by_key(|&i| header.lump[i].n_offset);
+        let mut lumps = vec![];
+        for i in order {
+            let ptr = header.lump[i];
+            let offset: u64 = ptr
+                .n_offset
+                .try_into()
+                .map_err(BspParseError::BadPointerValue)?;
+            let len: u64 = ptr
+                .n_length
+                .try_into()
+                .map_err(BspParseError::BadPointerValue)?;
+            if len == 0 {
+                continue;
+            }
+            stream.skip_to(offset, || LumpType(i).to_string())?;
+            let data = stream.read_up_to(len)?;
+            if (data.len() as u64) < len {
+                return Err(BspParseError::Lump(BspLumpError {
+                    lump: LumpType(i),
+                    ptr,
+                    element_size: 1,
+                    file_len: stream.position,
+                    kind: BspLumpErrorKind::PastEndOfFile,
+                }));
+            }
+            lumps.push((offset, data));
+        }
+        let bspx = stream_bspx(&mut stream, &header, options)?;
+        let mut streamed = StreamedLumps::new(lumps, stream.position);
+        let layout = Self::detect_layout(&mut streamed, &header)?;
+        let header = header.with_layout(layout);
+        let version = header.version().unwrap_or(BspVersion::GoldSrc30);
+        check_sizes(&header, version, options)?;
+        decode_lumps(&mut streamed, header, layout, options, |_| Ok(bspx))
+    }
+}
+
+/// Reads the BSPX lumps following the standard ones, if there are any.
+fn stream_bspx<R: Read>(
+    stream: &mut ForwardReader<R>,
+    header: &BspHeader,
+    options: &ParseOptions,
+) -> Result<Option<BspxLumps>, BspParseError> {
+    let offset = bspx_offset(header) as u64;
+    if !stream.skip_to(offset, || "BSPX header".to_owned())? {
+        return Ok(None);
+    }
+    let buffer = stream.read_up_to(std::mem::size_of::<BspxHeader>() as u64)?;
+    if buffer.len() < std::mem::size_of::<BspxHeader>() {
+        return Ok(None);
+    }
+    let bspx: BspxHeader = struct_at(&buffer, 0)?;
+    if bspx.sz_magic != BSPX_MAGIC {
+        return Ok(None);
+    }
+    let directory_len = (bspx.n_lumps.max(0) as u64) * std::mem::size_of::<BspxLumpEntry>() as u64;
+    check_bspx_directory(header, directory_len, options)?;
+    let buffer = stream.read_up_to(directory_len)?;
+    let entries: &[BspxLumpEntry] = match read_fully(buffer.len() as u64, directory_len)
+        .and_then(|()| try_cast_slice(&buffer).map_err(BspParseError::DeserializationError))
+    {
+        Ok(entries) => entries,
+        Err(error) => {
+            return Ok(Some(BspxLumps::new(
+                vec![],
+                vec![BspxLumpError {
+                    name: String::new(),
+                    error,
+                }],
+            )))
+        }
+    };
+    check_bspx_bytes(header, entries, options)?;
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by_key(|&i| entries[i].n_offset);
+    let mut data = vec![None; entries.len()];
+    let mut errors = vec![];
+    for i in order {
+        let entry = &entries[i];
+        match stream_bspx_lump(stream, entry) {
+            Ok(lump) => data[i] = Some(lump),
+            Err(error) => errors.push(BspxLumpError {
+                name: entry.name(),
+                error,
+            }),
+        }
+    }
+    let lumps = entries
+        .iter()
+        .zip(data)
+        .filter_map(|(entry, data)| {
+            Some(BspxLump {
+                name: entry.name(),
+                data: data?,
+            })
+        })
+        .collect();
+    Ok(Some(BspxLumps::new(lumps, errors)))
+}
+
+fn read_fully(read: u64, len: u64) -> Result<(), BspParseError> {
+    if read < len {
+        return Err(BspParseError::GenericError(
+            io::ErrorKind::UnexpectedEof.into(),
+        ));
+    }
+    Ok(())
+}
+
+fn stream_bspx_lump<R: Read>(
+    stream: &mut ForwardReader<R>,
+    entry: &BspxLumpEntry,
+) -> Result<Vec<u8>, BspParseError> {
+    let offset: u64 = entry
+        .n_offset
+        .try_into()
+        .map_err(BspParseError::BadPointerValue)?;
+    let len: u64 = entry
+        .n_length
+        .try_into()
+        .map_err(BspParseError::BadPointerValue)?;
+    if len == 0 {
+        return Ok(vec![]);
+    }
+    stream.skip_to(offset, || format!("BSPX lump {}", entry.name()))?;
+    let data = stream.read_up_to(len)?;
+    read_fully(data.len() as u64, len)?;
+    Ok(data)
+}
@@ -1,56 +1,146 @@
-use std::io::{Read, Seek, SeekFrom};
+use std::io::{Read, Seek};
 
-use bytemuck::try_cast_slice;
+use bytemuck::{try_cast_slice, Zeroable};
 
 use crate::{
-    header::{BspHeader, BspLumpPointer, LUMP_TEXINFO, LUMP_TEXTURES},
+    header::{BspHeader, BspLumpPointer, BspVersion, LumpType, LUMP_TEXINFO, LUMP_TEXTURES},
     lumps::{
         tex_info::{BspTexInfoLump, TexInfo},
-        textures::{BspMipTex, BspMipTexOffset, BspTextureHeader, BspTexturesLump},
+        textures::{
+            BspMipTex, BspMipTexOffset, BspMipTexPixels, BspPalette, BspTexture, BspTextureData,
+            BspTextureHeader, BspTexturesLump, MIP_LEVELS, PALETTE_SIZE,
+        },
     },
 };
 
 use super::{
-    extract_struct, seek_and_extract, seek_ptr, BspParseError, LumpExtractor, PtrLumpReader,
+    extract_with_context, seek_and_extract, slice_at, struct_at, BspParseError, LumpExtractor,
+    PtrLumpReader,
 };
 
+/// Decodes a palette prefixed by its 16-bit color count.
+pub fn decode_palette(data: &[u8], offset: usize) -> Result<BspPalette, BspParseError> {
+    let n_colors: u16 = struct_at(data, offset)?;
+    let n_colors = n_colors.min(PALETTE_SIZE as u16) as usize;
+    let colors = slice_at(data, offset + 2, n_colors * 3)?;
+    Ok(BspPalette(
+        colors
+            .chunks_exact(3)
+            .map(|rgb| [rgb[0], rgb[1], rgb[2]])
+            .collect(),
+    ))
+}
+
+/// Decodes a `BSPMIPTEX` and the pixels that follow it. `data` must start at
+/// the miptex header, as mip offsets are relative to it. The same layout is
+/// used by the miptex entries of WAD3 files. BSP29 textures have no palette
+/// after their last mip level, in which case `with_palette` must be unset.
+pub fn decode_mip_tex(data: &[u8], with_palette: bool) -> Result<BspTexture, BspParseError> {
+    let mip_tex: BspMipTex = struct_at(data, 0)?;
+    if mip_tex.is_external() {
+        return Ok(BspTexture {
+            mip_tex,
+            data: BspTextureData::External,
+            padding: vec![],
+        });
+    }
+    if mip_tex.n_offsets.contains(&0) {
+        return Err(BspParseError::PartialMipTex(mip_tex.n_offsets));
+    }
+    let mut mips: [Vec<u8>; MIP_LEVELS] = Default::default();
+    let mut end = 0;
+    for (level, mip) in mips.iter_mut().enumerate() {
+        let (width, height) = mip_tex.mip_size(level);
+        let offset: usize = mip_tex.n_offsets[level]
+            .try_into()
+            .map_err(BspParseError::BadPointerValue)?;
+        *mip = slice_at(data, offset, width * height)?.to_vec();
+        end = end.max(offset + width * height);
+    }
+    let palette = if with_palette {
+        Some(decode_palette(data, end)?)
+    } else {
+        None
+    };
+    Ok(BspTexture {
+        mip_tex,
+        data: BspTextureData::Embedded(BspMipTexPixels { mips, palette }),
+        padding: vec![],
+    })
+}
+
+fn read_textures<T: Seek + Read>(
+    read: &mut T,
+    ptr: &BspLumpPointer,
+    with_palette: bool,
+) -> Result<BspTexturesLump, BspParseError> {
+    let buffer = seek_and_extract(read, ptr)?;
+    let header: BspTextureHeader = struct_at(&buffer, 0)?;
+    let count: usize = header
+        .n_mip_textures
+        .try_into()
+        .map_err(BspParseError::BadPointerValue)?;
+    let offsets = (0..count)
+        .map(|i| struct_at::<BspMipTexOffset>(&buffer, 4 + i * 4))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut textures: Vec<BspTexture> = Vec::with_capacity(count);
+    for offset in &offsets {
+        if offset.0 == -1 {
+            textures.push(BspTexture {
+                mip_tex: BspMipTex::zeroed(),
+                data: BspTextureData::Missing,
+                padding: vec![],
+            });
+            continue;
+        }
+        let start: usize = offset
+            .0
+            .try_into()
+            .map_err(BspParseError::BadPointerValue)?;
+        let data = buffer.get(start..).unwrap_or_default();
+        let mut texture = decode_mip_tex(data, with_palette)?;
+        let next = offsets
+            .iter()
+            .filter(|next| next.0 > offset.0)
+            .map(|next| next.0 as usize)
+            .min()
+            .unwrap_or(buffer.len())
+            .min(buffer.len());
+        let end = start.saturating_add(texture.data_len());
+        texture.padding = buffer.get(end..next).unwrap_or_default().to_vec();
+        textures.push(texture);
+    }
+    Ok(BspTexturesLump(textures))
+}
+
 impl PtrLumpReader for BspTexturesLump {
     fn read_from_ptr<T>(read: &mut T, ptr: &BspLumpPointer) -> Result<Self, BspParseError>
     where
         T: Seek + Read,
         Self: Sized,
     {
-        seek_ptr(read, ptr)?;
-        let header: BspTextureHeader = extract_struct(read)?;
-        let count = header
-            .n_mip_textures
-            .try_into()
-            .map_err(BspParseError::BadPointerValue)?;
-        let mut mip_tex_offsets: Vec<BspMipTexOffset> = Vec::with_capacity(count);
-        for _ in 0..header.n_mip_textures {
-            let value: BspMipTexOffset = extract_struct(read)?;
-            mip_tex_offsets.push(value);
-        }
-        let mut textures: Vec<BspMipTex> = Vec::with_capacity(count);
-        for index in mip_tex_offsets {
-            let offset = index.0 + ptr.n_offset;
-            read.seek(SeekFrom::Start(
-                offset.try_into().map_err(BspParseError::BadPointerValue)?,
-            ))
-            .map_err(BspParseError::GenericError)?;
-            let mip: BspMipTex = extract_struct(read)?;
-            textures.push(mip);
-        }
-        Ok(BspTexturesLump(textures))
+        read_textures(read, ptr, true)
     }
 }
 impl LumpExtractor<BspTexturesLump> for BspHeader {
+    const LUMP: LumpType = LUMP_TEXTURES;
+
     fn get_pointer(&self) -> BspLumpPointer {
         self.lump[LUMP_TEXTURES.0]
     }
+
+    fn extract_lump<T: Seek + Read>(&self, read: &mut T) -> Result<BspTexturesLump, BspParseError> {
+        let with_palette = self.version().is_none_or(BspVersion::has_texture_palettes);
+        let ptr = self.lump[LUMP_TEXTURES.0];
+        extract_with_context(read, LUMP_TEXTURES, ptr, 1, |read| {
+            read_textures(read, &ptr, with_palette)
+        })
+    }
 }
 
 impl PtrLumpReader for BspTexInfoLump {
+    const ELEMENT_SIZE: usize = std::mem::size_of::<TexInfo>();
+
     fn read_from_ptr<T>(read: &mut T, ptr: &BspLumpPointer) -> Result<Self, BspParseError>
     where
         T: Seek + Read,
@@ -63,6 +153,8 @@ impl PtrLumpReader for BspTexInfoLump {
     }
 }
 impl LumpExtractor<BspTexInfoLump> for BspHeader {
+    const LUMP: LumpType = LUMP_TEXINFO;
+
     fn get_pointer(&self) -> BspLumpPointer {
         self.lump[LUMP_TEXINFO.0]
     }
@@ -1,8 +1,67 @@
+use std::fmt;
+
 use crate::{
     bsp::Bsp,
-    lumps::{faces::BspFace, vertices::BspVertex},
+    header::{
+        LumpType, LUMP_EDGES, LUMP_FACES, LUMP_LEAVES, LUMP_MARKSURFACES, LUMP_MODELS, LUMP_PLANES,
+        LUMP_SURFEDGES, LUMP_TEXINFO, LUMP_TEXTURES, LUMP_VERTICES,
+    },
+    lumps::{
+        clip_nodes::BspClipNode, faces::BspFace, leaves::BspLeaf, models::BspModel, nodes::BspNode,
+        planes::BspPlane, surfaces::BspEdge, tex_info::TexInfo, textures::BspTexture,
+        vertices::BspVertex, vis::LeafSet,
+    },
 };
 
+/// An index into a lump that is out of its range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelationError {
+    pub lump: LumpType,
+    pub index: i64,
+    /// Number of elements of the lump.
+    pub len: usize,
+}
+
+impl fmt::Display for RelationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "index {} is out of range of the {} ({} elements)",
+            self.index, self.lump, self.len
+        )
+    }
+}
+
+impl std::error::Error for RelationError {}
+
+fn lookup<T>(lump: LumpType, items: &[T], index: i64) -> Result<&T, RelationError> {
+    usize::try_from(index)
+        .ok()
+        .and_then(|i| items.get(i))
+        .ok_or(RelationError {
+            lump,
+            index,
+            len: items.len(),
+        })
+}
+
+fn lookup_range<T>(
+    lump: LumpType,
+    items: &[T],
+    first: i64,
+    count: i64,
+) -> Result<&[T], RelationError> {
+    let range = usize::try_from(first)
+        .ok()
+        .zip(usize::try_from(count).ok())
+        .and_then(|(first, count)| items.get(first..first.checked_add(count)?));
+    range.ok_or(RelationError {
+        lump,
+        index: first.saturating_add(count).saturating_sub(1),
+        len: items.len(),
+    })
+}
+
 impl BspFace {
     /// # Vertices from a face
     ///
@@ -29,7 +88,150 @@ impl BspFace {
     }
 }
 
+/// # Fallible relations
+///
+/// The accessors below follow the indices between lumps like the `Index`
+/// implementations of the lumps do, but return a `RelationError` instead of
+/// panicking when an index is out of range, for maps that can't be trusted.
+/// `Bsp::validate` reports every such index at once.
+impl BspFace {
+    pub fn try_plane<'a>(&self, bsp: &'a Bsp) -> Result<&'a BspPlane, RelationError> {
+        lookup(LUMP_PLANES, &bsp.planes.0, self.i_plane as i64)
+    }
+
+    pub fn try_texinfo<'a>(&self, bsp: &'a Bsp) -> Result<&'a TexInfo, RelationError> {
+        lookup(LUMP_TEXINFO, &bsp.tex_info.0, self.i_texture_info as i64)
+    }
+
+    pub fn try_miptex<'a>(&self, bsp: &'a Bsp) -> Result<&'a BspTexture, RelationError> {
+        self.try_texinfo(bsp)?.try_miptex(bsp)
+    }
+
+    /// The edges of the face in winding order, each one oriented so that
+    /// its first vertex comes first in the winding.
+    pub fn try_edges(&self, bsp: &Bsp) -> Result<Vec<BspEdge>, RelationError> {
+        let surf_edges = lookup_range(
+            LUMP_SURFEDGES,
+            &bsp.surf_edges.0,
+            self.i_first_edge as i64,
+            self.n_edges as i64,
+        )?;
+        surf_edges
+            .iter()
+            .map(|surf_edge| {
+                let index = surf_edge.0 as i64;
+                let edge = lookup(LUMP_EDGES, &bsp.edges.0, index.abs())?;
+                Ok(if index < 0 {
+                    BspEdge {
+                        i_vertex: [edge.i_vertex[1], edge.i_vertex[0]],
+                    }
+                } else {
+                    *edge
+                })
+            })
+            .collect()
+    }
+
+    /// Fallible version of `BspFace::vertices`.
+    pub fn try_vertices<'a>(&self, bsp: &'a Bsp) -> Result<Vec<&'a BspVertex>, RelationError> {
+        self.try_edges(bsp)?
+            .iter()
+            .map(|edge| lookup(LUMP_VERTICES, &bsp.vertices.0, edge.i_vertex[0] as i64))
+            .collect()
+    }
+}
+
+impl TexInfo {
+    pub fn try_miptex<'a>(&self, bsp: &'a Bsp) -> Result<&'a BspTexture, RelationError> {
+        lookup(LUMP_TEXTURES, &bsp.textures.0, self.miptex_index as i64)
+    }
+}
+
+impl BspNode {
+    pub fn try_plane<'a>(&self, bsp: &'a Bsp) -> Result<&'a BspPlane, RelationError> {
+        lookup(LUMP_PLANES, &bsp.planes.0, self.plane_index as i64)
+    }
+
+    pub fn try_faces<'a>(&self, bsp: &'a Bsp) -> Result<&'a [BspFace], RelationError> {
+        lookup_range(
+            LUMP_FACES,
+            &bsp.faces.0,
+            self.first_face as i64,
+            self.n_faces as i64,
+        )
+    }
+}
+
+impl BspClipNode {
+    pub fn try_plane<'a>(&self, bsp: &'a Bsp) -> Result<&'a BspPlane, RelationError> {
+        lookup(LUMP_PLANES, &bsp.planes.0, self.i_plane as i64)
+    }
+}
+
+impl BspLeaf {
+    /// The faces listed by the marksurfaces of the leaf.
+    pub fn try_faces<'a>(&self, bsp: &'a Bsp) -> Result<Vec<&'a BspFace>, RelationError> {
+        lookup_range(
+            LUMP_MARKSURFACES,
+            &bsp.mark_surfaces.0,
+            self.i_fist_mark_surface as i64,
+            self.n_mark_surfaces as i64,
+        )?
+        .iter()
+        .map(|mark_surface| lookup(LUMP_FACES, &bsp.faces.0, mark_surface.0 as i64))
+        .collect()
+    }
+}
+
+impl BspModel {
+    pub fn try_faces<'a>(&self, bsp: &'a Bsp) -> Result<&'a [BspFace], RelationError> {
+        lookup_range(
+            LUMP_FACES,
+            &bsp.faces.0,
+            self.i_first_face as i64,
+            self.n_faces as i64,
+        )
+    }
+}
+
 impl Bsp {
+    pub fn try_face(&self, index: usize) -> Result<&BspFace, RelationError> {
+        lookup(LUMP_FACES, &self.faces.0, index as i64)
+    }
+
+    pub fn try_leaf(&self, index: usize) -> Result<&BspLeaf, RelationError> {
+        lookup(LUMP_LEAVES, &self.leaves.0, index as i64)
+    }
+
+    pub fn try_model(&self, index: usize) -> Result<&BspModel, RelationError> {
+        lookup(LUMP_MODELS, &self.models.0, index as i64)
+    }
+
+    /// The faces of the leaf with the given index.
+    pub fn try_leaf_faces(&self, leaf_index: usize) -> Result<Vec<&BspFace>, RelationError> {
+        self.try_leaf(leaf_index)?.try_faces(self)
+    }
+
+    /// # Potentially visible set of a leaf
+    ///
+    /// Decodes the VIS row of the given leaf into the set of leaves that can
+    /// be seen from it. Leaves without VIS data (offset -1), as well as maps
+    /// compiled without VIS at all, see everything. Fails if there's no such
+    /// leaf.
+    pub fn pvs(&self, leaf_index: usize) -> Result<LeafSet, RelationError> {
+        let n_vis_leafs = self
+            .models
+            .0
+            .first()
+            .map_or(0, |world| world.n_vis_leafs.max(0) as usize);
+        let offset = self.try_leaf(leaf_index)?.n_vis_offset;
+        if offset < 0 || self.vis.0.is_empty() {
+            return Ok(LeafSet::all_visible(n_vis_leafs));
+        }
+        let row = self.vis.decompress(offset as usize, n_vis_leafs);
+        Ok(LeafSet::from_vis_row(&row, n_vis_leafs))
+    }
+
     /// # Brushes iterator
     ///
     /// This function provides an iterator of descriptors,
@@ -0,0 +1,147 @@
+use crate::{
+    bsp::Bsp,
+    lumps::{
+        leaves::BspLeaf,
+        nodes::{BspNode, BspNodeChild},
+    },
+    math::Vector3D,
+};
+
+/// # BSP node tree
+///
+/// A borrowed view over the rendering BSP tree of a single model, rooted at
+/// `BspModel::i_head_nodes[0]`. The world is always model 0, while brush
+/// entities have their own, smaller trees.
+#[derive(Debug, Clone, Copy)]
+pub struct BspNodeTree<'a> {
+    bsp: &'a Bsp,
+    model: usize,
+    root: BspNodeChild,
+}
+
+/// An element visited while walking a `BspNodeTree`.
+#[derive(Debug, Clone, Copy)]
+pub enum BspTreeItem<'a> {
+    Node {
+        index: usize,
+        depth: usize,
+        node: &'a BspNode,
+    },
+    Leaf {
+        index: usize,
+        depth: usize,
+        leaf: &'a BspLeaf,
+    },
+}
+
+impl<'a> BspNodeTree<'a> {
+    /// Index of the model this tree belongs to.
+    pub fn model(&self) -> usize {
+        self.model
+    }
+
+    pub fn root(&self) -> BspNodeChild {
+        self.root
+    }
+
+    /// Looks up the node behind a child reference, if it is a node.
+    pub fn node(&self, child: BspNodeChild) -> Option<&'a BspNode> {
+        match child {
+            BspNodeChild::Node(index) => self.bsp.nodes.0.get(index),
+            BspNodeChild::Leaf(_) => None,
+        }
+    }
+
+    /// Depth-first, front-to-back walk of the tree. Dangling indices are
+    /// skipped and every node is visited at most once, so malformed files
+    /// can't make the walk loop forever.
+    pub fn iter(&self) -> BspNodeTreeIter<'a> {
+        BspNodeTreeIter {
+            bsp: self.bsp,
+            stack: vec![(self.root, 0)],
+            visited: vec![false; self.bsp.nodes.0.len()],
+        }
+    }
+
+    /// Indices of all the leaves reachable from the root.
+    pub fn leaves(&self) -> impl Iterator<Item = usize> + 'a {
+        self.iter().filter_map(|item| match item {
+            BspTreeItem::Leaf { index, .. } => Some(index),
+            BspTreeItem::Node { .. } => None,
+        })
+    }
+
+    /// Finds the leaf that contains the given point, descending to the front
+    /// child when the point lies on or in front of the splitting plane.
+    pub fn find_leaf(&self, point: &Vector3D) -> Option<usize> {
+        let mut current = self.root;
+        for _ in 0..=self.bsp.nodes.0.len() {
+            let node = match current {
+                BspNodeChild::Leaf(index) => return Some(index),
+                BspNodeChild::Node(index) => self.bsp.nodes.0.get(index)?,
+            };
+            let plane = self.bsp.planes.0.get(node.plane_index as usize)?;
+            let [front, back] = node.children();
+            current = if plane.v_normal.dot(point) - plane.f_dist >= 0.0 {
+                front
+            } else {
+                back
+            };
+        }
+        None
+    }
+}
+
+pub struct BspNodeTreeIter<'a> {
+    bsp: &'a Bsp,
+    stack: Vec<(BspNodeChild, usize)>,
+    visited: Vec<bool>,
+}
+
+impl<'a> Iterator for BspNodeTreeIter<'a> {
+    type Item = BspTreeItem<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((child, depth)) = self.stack.pop() {
+            match child {
+                BspNodeChild::Leaf(index) => {
+                    if let Some(leaf) = self.bsp.leaves.0.get(index) {
+                        return Some(BspTreeItem::Leaf { index, depth, leaf });
+                    }
+                }
+                BspNodeChild::Node(index) => {
+                    let Some(node) = self.bsp.nodes.0.get(index) else {
+                        continue;
+                    };
+                    if std::mem::replace(&mut self.visited[index], true) {
+                        continue;
+                    }
+                    let [front, back] = node.children();
+                    self.stack.push((back, depth + 1));
+                    self.stack.push((front, depth + 1));
+                    return Some(BspTreeItem::Node { index, depth, node });
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Bsp {
+    /// # Node trees
+    ///
+    /// Returns one tree per model, each rooted at the model's first head
+    /// node. The first tree is the world.
+    pub fn node_tree(&self) -> Vec<BspNodeTree<'_>> {
+        self.models
+            .0
+            .iter()
+            .enumerate()
+            .map(|(model, m)| BspNodeTree {
+                bsp: self,
+                model,
+                root: BspNodeChild::Node(m.i_head_nodes[0] as usize),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::{self, Write},
+};
+
+use crate::lumps::entities::{BspEntitiesLump, BspEntity};
+
+/// # Entity index
+///
+/// The indices of the entities of a lump by `classname` and by
+/// `targetname`. Names are matched exactly, like the game does.
+#[derive(Debug, Clone, Default)]
+pub struct EntityIndex {
+    classnames: HashMap<String, Vec<usize>>,
+    targetnames: HashMap<String, Vec<usize>>,
+}
+
+impl EntityIndex {
+    pub fn new(entities: &BspEntitiesLump) -> Self {
+        let mut index = Self::default();
+        for (i, entity) in entities.0.iter().enumerate() {
+            if let Some(classname) = entity.classname() {
+                index
+                    .classnames
+                    .entry(classname.into_owned())
+                    .or_default()
+                    .push(i);
+            }
+            if let Some(targetname) = entity.targetname() {
+                index
+                    .targetnames
+                    .entry(targetname.into_owned())
+                    .or_default()
+                    .push(i);
+            }
+        }
+        index
+    }
+
+    pub fn by_classname(&self, classname: &str) -> &[usize] {
+        self.classnames.get(classname).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn by_targetname(&self, targetname: &str) -> &[usize] {
+        self.targetnames.get(targetname).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn classnames(&self) -> impl Iterator<Item = &str> {
+        self.classnames.keys().map(String::as_str)
+    }
+
+    pub fn targetnames(&self) -> impl Iterator<Item = &str> {
+        self.targetnames.keys().map(String::as_str)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriggerEdgeKind {
+    /// The `target` key, fired when the entity triggers.
+    Target,
+    /// The `killtarget` key, removed when the entity triggers.
+    KillTarget,
+    /// A key of a `multi_manager`, fired `delay` seconds after it.
+    MultiManager { delay: f32 },
+    /// The `master` key, the entity only works while its master, usually a
+    /// `multisource`, is on.
+    Master,
+}
+
+impl TriggerEdgeKind {
+    /// Whether the edge fires its target, as opposed to only reading its
+    /// state.
+    pub fn fires(&self) -> bool {
+        !matches!(self, Self::Master)
+    }
+}
+
+impl fmt::Display for TriggerEdgeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Target => write!(f, "target"),
+            Self::KillTarget => write!(f, "killtarget"),
+            Self::MultiManager { delay } => write!(f, "multi_manager {delay}s"),
+            Self::Master => write!(f, "master"),
+        }
+    }
+}
+
+/// A reference from an entity to the entities named `target`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriggerEdge {
+    /// Index of the entity holding the reference.
+    pub from: usize,
+    pub target: String,
+    pub kind: TriggerEdgeKind,
+}
+
+/// Keys of a `multi_manager` which aren't targets: the ones the engine
+/// stores in the entity variables, and its own `wait`.
+const MULTI_MANAGER_KEYS: [&str; 9] = [
+    "classname",
+    "targetname",
+    "target",
+    "origin",
+    "angles",
+    "angle",
+    "spawnflags",
+    "globalname",
+    "wait",
+];
+
+fn edges_of(i: usize, entity: &BspEntity, edges: &mut Vec<TriggerEdge>) {
+    let mut push = |target: &str, kind| {
+        if !target.is_empty() {
+            edges.push(TriggerEdge {
+                from: i,
+                target: target.to_owned(),
+                kind,
+            });
+        }
+    };
+    for (key, value) in entity.iter() {
+        match key.as_bytes() {
+            b"target" => push(&value.to_str(), TriggerEdgeKind::Target),
+            b"killtarget" => push(&value.to_str(), TriggerEdgeKind::KillTarget),
+            b"master" => push(&value.to_str(), TriggerEdgeKind::Master),
+            _ => {}
+        }
+    }
+    if entity.classname().as_deref() != Some("multi_manager") {
+        return;
+    }
+    for (key, value) in entity.iter() {
+        let key = key.to_str();
+        if MULTI_MANAGER_KEYS.contains(&key.as_ref()) {
+            continue;
+        }
+        // Repeated targets are told apart with a `#n` suffix, which the game
+        // strips.
+        let target = key.split('#').next().unwrap_or_default();
+        // Like `atof`, an unreadable delay is no delay.
+        let delay = value.to_str().trim().parse().unwrap_or(0.0);
+        push(target, TriggerEdgeKind::MultiManager { delay });
+    }
+}
+
+/// # Trigger graph
+///
+/// How the entities of a map trigger each other: every `target`,
+/// `killtarget`, `master` and `multi_manager` key is an edge from its
+/// entity to the entities with that `targetname`. A `multisource` appears
+/// as the target of its inputs and the `master` of the entities it gates.
+#[derive(Debug, Clone)]
+pub struct TriggerGraph<'a> {
+    entities: &'a BspEntitiesLump,
+    pub index: EntityIndex,
+    /// The edges, ordered by entity and then by key.
+    pub edges: Vec<TriggerEdge>,
+}
+
+impl<'a> TriggerGraph<'a> {
+    pub fn new(entities: &'a BspEntitiesLump) -> Self {
+        let mut edges = vec![];
+        for (i, entity) in entities.0.iter().enumerate() {
+            edges_of(i, entity, &mut edges);
+        }
+        Self {
+            entities,
+            index: EntityIndex::new(entities),
+            edges,
+        }
+    }
+
+    /// The entities an edge points to, empty if its target is undefined.
+    pub fn targets(&self, edge: &TriggerEdge) -> &[usize] {
+        self.index.by_targetname(&edge.target)
+    }
+
+    /// The edges leaving an entity.
+    pub fn outgoing(&self, entity: usize) -> impl Iterator<Item = &TriggerEdge> {
+        self.edges.iter().filter(move |edge| edge.from == entity)
+    }
+
+    /// The edges pointing at an entity.
+    pub fn incoming(&self, entity: usize) -> impl Iterator<Item = &TriggerEdge> + '_ {
+        let targetname = self.entities.0.get(entity).and_then(BspEntity::targetname);
+        self.edges
+            .iter()
+            .filter(move |edge| targetname.as_deref() == Some(edge.target.as_str()))
+    }
+
+    /// Edges whose target no entity defines.
+    pub fn undefined_targets(&self) -> impl Iterator<Item = &TriggerEdge> {
+        self.edges
+            .iter()
+            .filter(|edge| self.targets(edge).is_empty())
+    }
+
+    /// The `targetname`s that no edge fires, sorted.
+    pub fn unfired_targetnames(&self) -> Vec<&str> {
+        let fired: BTreeSet<&str> = self
+            .edges
+            .iter()
+            .filter(|edge| edge.kind.fires())
+            .map(|edge| edge.target.as_str())
+            .collect();
+        let mut unfired: Vec<&str> = self
+            .index
+            .targetnames()
+            .filter(|name| !fired.contains(name))
+            .collect();
+        unfired.sort_unstable();
+        unfired
+    }
+
+    /// # DOT export
+    ///
+    /// Writes the graph in the Graphviz DOT language. Entities are named
+    /// `e<index>` and labelled with their classname and targetname; only
+    /// the ones with a targetname or an edge are written. Undefined targets
+    /// are dashed boxes.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph triggers {\n");
+        for (i, entity) in self.entities.0.iter().enumerate() {
+            let targetname = entity.targetname();
+            if targetname.is_none() && self.outgoing(i).next().is_none() {
+                continue;
+            }
+            let mut label = entity.classname().unwrap_or_default().into_owned();
+            if let Some(targetname) = targetname {
+                label.push('\n');
+                label.push_str(&targetname);
+            }
+            let _ = writeln!(dot, "  e{i} [label=\"{}\"];", escape(&label));
+        }
+        let mut undefined = BTreeSet::new();
+        for edge in &self.edges {
+            let style = match edge.kind {
+                TriggerEdgeKind::Master => ", style=dashed",
+                TriggerEdgeKind::KillTarget => ", color=red",
+                _ => "",
+            };
+            let attributes = format!("[label=\"{}\"{style}]", escape(&edge.kind.to_string()));
+            let targets = self.targets(edge);
+            if targets.is_empty() {
+                undefined.insert(edge.target.as_str());
+                let target = escape(&edge.target);
+                let _ = writeln!(dot, "  e{} -> \"?{target}\" {attributes};", edge.from);
+            }
+            for target in targets {
+                let _ = writeln!(dot, "  e{} -> e{target} {attributes};", edge.from);
+            }
+        }
+        for target in undefined {
+            let target = escape(target);
+            let _ = writeln!(
+                dot,
+                "  \"?{target}\" [label=\"{target}\", shape=box, style=dashed];"
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl BspEntitiesLump {
+    pub fn index(&self) -> EntityIndex {
+        EntityIndex::new(self)
+    }
+
+    pub fn trigger_graph(&self) -> TriggerGraph<'_> {
+        TriggerGraph::new(self)
+    }
+}
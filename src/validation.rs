@@ -0,0 +1,251 @@
+use std::fmt;
+
+use crate::{
+    bsp::Bsp,
+    header::{
+        LumpType, LUMP_CLIPNODES, LUMP_EDGES, LUMP_FACES, LUMP_LEAVES, LUMP_MARKSURFACES,
+        LUMP_MODELS, LUMP_NODES, LUMP_PLANES, LUMP_SURFEDGES, LUMP_TEXINFO, LUMP_TEXTURES,
+        LUMP_VERTICES,
+    },
+    lumps::nodes::BspNodeChild,
+};
+
+/// A reference from one element of a lump to elements of another one that
+/// don't exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    /// The lump holding the bad reference.
+    pub lump: LumpType,
+    /// Index of the element holding the bad reference.
+    pub index: usize,
+    /// Name of the field holding the bad reference.
+    pub field: &'static str,
+    /// The lump being referenced.
+    pub target: LumpType,
+    /// The referenced range, `first..first + count`. Single references have
+    /// a count of 1.
+    pub first: i64,
+    pub count: i64,
+    /// Number of elements of the referenced lump.
+    pub target_len: usize,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}].{} ", self.lump.name(), self.index, self.field)?;
+        if self.count == 1 {
+            write!(f, "= {}", self.first)?;
+        } else {
+            write!(f, "= {}..{}", self.first, self.first + self.count)?;
+        }
+        write!(
+            f,
+            " is out of range of the {} ({} elements)",
+            self.target, self.target_len
+        )
+    }
+}
+
+/// # Validation report
+///
+/// Every out of range reference between the lumps of a map, in lump order.
+/// A map with an empty report can be walked with the panicking `Index`
+/// implementations of the lumps.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// The issues found in the given lump.
+    pub fn issues_in(&self, lump: LumpType) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(move |issue| issue.lump == lump)
+    }
+
+    fn check(
+        &mut self,
+        (lump, index, field): (LumpType, usize, &'static str),
+        target: LumpType,
+        first: i64,
+        count: i64,
+        target_len: usize,
+    ) {
+        if first >= 0 && count >= 0 && first + count <= target_len as i64 {
+            return;
+        }
+        self.issues.push(ValidationIssue {
+            lump,
+            index,
+            field,
+            target,
+            first,
+            count,
+            target_len,
+        });
+    }
+}
+
+impl Bsp {
+    /// # Referential integrity
+    ///
+    /// Checks every index a lump holds into another lump: faces to planes,
+    /// texinfo and surfedges, surfedges to edges, edges to vertices, leaves to
+    /// marksurfaces, marksurfaces to faces, texinfo to textures, nodes and
+    /// clipnodes to planes and children, and models to head nodes and faces.
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let planes = self.planes.0.len();
+        let faces = self.faces.0.len();
+        let nodes = self.nodes.0.len();
+        let clip_nodes = self.clip_nodes.0.len();
+        let leaves = self.leaves.0.len();
+
+        for (i, face) in self.faces.0.iter().enumerate() {
+            let at = |field| (LUMP_FACES, i, field);
+            report.check(at("i_plane"), LUMP_PLANES, face.i_plane as i64, 1, planes);
+            report.check(
+                at("i_texture_info"),
+                LUMP_TEXINFO,
+                face.i_texture_info as i64,
+                1,
+                self.tex_info.0.len(),
+            );
+            report.check(
+                at("i_first_edge"),
+                LUMP_SURFEDGES,
+                face.i_first_edge as i64,
+                face.n_edges as i64,
+                self.surf_edges.0.len(),
+            );
+        }
+        for (i, surf_edge) in self.surf_edges.0.iter().enumerate() {
+            report.check(
+                (LUMP_SURFEDGES, i, "0"),
+                LUMP_EDGES,
+                (surf_edge.0 as i64).abs(),
+                1,
+                self.edges.0.len(),
+            );
+        }
+        for (i, edge) in self.edges.0.iter().enumerate() {
+            for (field, vertex) in ["i_vertex[0]", "i_vertex[1]"]
+                .into_iter()
+                .zip(edge.i_vertex)
+            {
+                report.check(
+                    (LUMP_EDGES, i, field),
+                    LUMP_VERTICES,
+                    vertex as i64,
+                    1,
+                    self.vertices.0.len(),
+                );
+            }
+        }
+        for (i, leaf) in self.leaves.0.iter().enumerate() {
+            report.check(
+                (LUMP_LEAVES, i, "i_fist_mark_surface"),
+                LUMP_MARKSURFACES,
+                leaf.i_fist_mark_surface as i64,
+                leaf.n_mark_surfaces as i64,
+                self.mark_surfaces.0.len(),
+            );
+        }
+        for (i, mark_surface) in self.mark_surfaces.0.iter().enumerate() {
+            report.check(
+                (LUMP_MARKSURFACES, i, "0"),
+                LUMP_FACES,
+                mark_surface.0 as i64,
+                1,
+                faces,
+            );
+        }
+        for (i, tex_info) in self.tex_info.0.iter().enumerate() {
+            report.check(
+                (LUMP_TEXINFO, i, "miptex_index"),
+                LUMP_TEXTURES,
+                tex_info.miptex_index as i64,
+                1,
+                self.textures.0.len(),
+            );
+        }
+        for (i, node) in self.nodes.0.iter().enumerate() {
+            let at = |field| (LUMP_NODES, i, field);
+            report.check(
+                at("plane_index"),
+                LUMP_PLANES,
+                node.plane_index as i64,
+                1,
+                planes,
+            );
+            for (field, child) in ["children_indices[0]", "children_indices[1]"]
+                .into_iter()
+                .zip(node.children())
+            {
+                match child {
+                    BspNodeChild::Node(child) => {
+                        report.check(at(field), LUMP_NODES, child as i64, 1, nodes)
+                    }
+                    BspNodeChild::Leaf(child) => {
+                        report.check(at(field), LUMP_LEAVES, child as i64, 1, leaves)
+                    }
+                }
+            }
+            report.check(
+                at("first_face"),
+                LUMP_FACES,
+                node.first_face as i64,
+                node.n_faces as i64,
+                faces,
+            );
+        }
+        for (i, clip_node) in self.clip_nodes.0.iter().enumerate() {
+            let at = |field| (LUMP_CLIPNODES, i, field);
+            report.check(
+                at("i_plane"),
+                LUMP_PLANES,
+                clip_node.i_plane as i64,
+                1,
+                planes,
+            );
+            // Negative children are contents, not indices.
+            for (field, child) in ["i_children[0]", "i_children[1]"]
+                .into_iter()
+                .zip(clip_node.i_children)
+                .filter(|(_, child)| *child >= 0)
+            {
+                report.check(at(field), LUMP_CLIPNODES, child as i64, 1, clip_nodes);
+            }
+        }
+        for (i, model) in self.models.0.iter().enumerate() {
+            let at = |field| (LUMP_MODELS, i, field);
+            report.check(
+                at("i_head_nodes[0]"),
+                LUMP_NODES,
+                model.i_head_nodes[0] as i64,
+                1,
+                nodes,
+            );
+            // Clipping hulls may be empty, in which case their head is a
+            // negative content value.
+            for (field, head) in ["i_head_nodes[1]", "i_head_nodes[2]", "i_head_nodes[3]"]
+                .into_iter()
+                .zip(&model.i_head_nodes[1..])
+                .filter(|(_, head)| **head >= 0)
+            {
+                report.check(at(field), LUMP_CLIPNODES, *head as i64, 1, clip_nodes);
+            }
+            report.check(
+                at("i_first_face"),
+                LUMP_FACES,
+                model.i_first_face as i64,
+                model.n_faces as i64,
+                faces,
+            );
+        }
+        report
+    }
+}
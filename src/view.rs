@@ -0,0 +1,240 @@
+use std::io::Cursor;
+
+use bytemuck::{try_cast_slice, Pod};
+
+use crate::{
+    bsp::Bsp,
+    header::{
+        BspHeader, BspLumpLayout, BspVersion, LumpType, LUMP_CLIPNODES, LUMP_EDGES, LUMP_ENTITIES,
+        LUMP_FACES, LUMP_LEAVES, LUMP_LIGHTING, LUMP_MARKSURFACES, LUMP_MODELS, LUMP_NODES,
+        LUMP_PLANES, LUMP_SURFEDGES, LUMP_TEXINFO, LUMP_TEXTURES, LUMP_VERTICES, LUMP_VISIBILITY,
+    },
+    lumps::{
+        clip_nodes::BspClipNode,
+        entities::BspEntitiesLump,
+        faces::BspFace,
+        leaves::BspLeaf,
+        models::BspModel,
+        nodes::BspNode,
+        planes::BspPlane,
+        surfaces::{BspEdge, BspMarkSurface, BspSurfEdge},
+        tex_info::TexInfo,
+        textures::{BspMipTex, BspMipTexOffset, BspTexture, MIP_LEVELS},
+        vertices::BspVertex,
+    },
+    parsing::{
+        decoding::{slice_at, struct_at, BspLumpError, BspParseError},
+        entities::{decode_entities, decode_entities_recovering, BspEntityParseError},
+        textures::decode_mip_tex,
+    },
+};
+
+/// # Borrowed BSP view
+///
+/// A read-only view over a whole BSP file already in memory, which hands out
+/// the lumps as slices of it instead of copying them. Only the header is read
+/// when building the view; the entity text and the textures are parsed when
+/// asked for.
+///
+/// Lumps are cast in place with `bytemuck::try_cast_slice`, so they must be
+/// aligned for their element type in memory: the buffer has to start at a 4
+/// byte aligned address, and every lump at a multiple of 4 within it, as the
+/// compilers lay them out. A `Vec<u8>`, such as the one `std::fs::read`
+/// returns, gives no such guarantee, and neither does a slice of a larger
+/// buffer. Misaligned lumps fail with `BspParseError::DeserializationError`;
+/// copying the file into a `Vec<u32>` and viewing it with
+/// `bytemuck::cast_slice` gives an aligned buffer. The entities, the
+/// textures and the byte lumps don't need any alignment.
+///
+/// Unlike `Bsp`, the view shows the lumps as they are stored: BSP29 lighting
+/// is one byte per sample, and BSP29 face lightmap offsets count those bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct BspView<'a> {
+    data: &'a [u8],
+    /// The header in the standard layout.
+    pub header: BspHeader,
+    pub version: BspVersion,
+    pub layout: BspLumpLayout,
+}
+
+impl<'a> BspView<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self, BspParseError> {
+        let (header, layout) = Bsp::extract_normalized_header(&mut Cursor::new(data))?;
+        Ok(Self {
+            data,
+            header,
+            version: header.version().unwrap_or(BspVersion::GoldSrc30),
+            layout,
+        })
+    }
+
+    /// The whole buffer the view was built from.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Raw bytes of the given lump.
+    pub fn lump(&self, lump: LumpType) -> Result<&'a [u8], BspParseError> {
+        self.lump_of(lump, 1)
+    }
+
+    fn lump_of(&self, lump: LumpType, element_size: usize) -> Result<&'a [u8], BspParseError> {
+        let ptr = self.header.lump[lump.0];
+        BspLumpError::check(lump, ptr, element_size, self.data.len() as u64)?;
+        Ok(&self.data[ptr.n_offset as usize..][..ptr.n_length as usize])
+    }
+
+    fn cast_lump<T: Pod>(&self, lump: LumpType) -> Result<&'a [T], BspParseError> {
+        let element_size = std::mem::size_of::<T>();
+        try_cast_slice(self.lump_of(lump, element_size)?).map_err(|err| {
+            BspLumpError::wrap(
+                lump,
+                self.header.lump[lump.0],
+                element_size,
+                self.data.len() as u64,
+                BspParseError::DeserializationError(err),
+            )
+        })
+    }
+
+    /// Parses the entity text. Nothing is cached, every call parses again.
+    pub fn entities(&self) -> Result<BspEntitiesLump, BspParseError> {
+        decode_entities(self.lump(LUMP_ENTITIES)?)
+    }
+
+    /// Parses the entity text, skipping the entities with errors, see
+    /// `decode_entities_recovering`.
+    pub fn entities_recovering(
+        &self,
+    ) -> Result<(BspEntitiesLump, Vec<BspEntityParseError>), BspParseError> {
+        Ok(decode_entities_recovering(self.lump(LUMP_ENTITIES)?))
+    }
+
+    pub fn planes(&self) -> Result<&'a [BspPlane], BspParseError> {
+        self.cast_lump(LUMP_PLANES)
+    }
+
+    /// Number of textures in the texture lump.
+    pub fn texture_count(&self) -> Result<usize, BspParseError> {
+        let count: i32 = struct_at(self.lump(LUMP_TEXTURES)?, 0)?;
+        count.try_into().map_err(BspParseError::BadPointerValue)
+    }
+
+    /// The offset table of the texture lump, relative to its start.
+    pub fn texture_offsets(&self) -> Result<&'a [BspMipTexOffset], BspParseError> {
+        let count = self.texture_count()?;
+        let table = slice_at(self.lump(LUMP_TEXTURES)?, 4, count.saturating_mul(4))?;
+        try_cast_slice(table).map_err(BspParseError::DeserializationError)
+    }
+
+    /// Bytes of the texture lump starting at the header of the given
+    /// texture, or `None` for missing textures.
+    fn texture_data(&self, index: usize) -> Result<Option<&'a [u8]>, BspParseError> {
+        let len = self.texture_count()?;
+        if index >= len {
+            return Err(BspParseError::IndexOutOfRange {
+                kind: "texture",
+                index,
+                len,
+            });
+        }
+        let offset: i32 = struct_at(self.lump(LUMP_TEXTURES)?, 4 + index * 4)?;
+        if offset == -1 {
+            return Ok(None);
+        }
+        let offset: usize = offset.try_into().map_err(BspParseError::BadPointerValue)?;
+        Ok(Some(
+            self.lump(LUMP_TEXTURES)?.get(offset..).unwrap_or_default(),
+        ))
+    }
+
+    /// Header of the given texture, or `None` for missing textures. The header
+    /// is copied out, as textures are not necessarily aligned in the lump.
+    pub fn mip_tex(&self, index: usize) -> Result<Option<BspMipTex>, BspParseError> {
+        self.texture_data(index)?
+            .map(|data| struct_at(data, 0))
+            .transpose()
+    }
+
+    /// Palette indices of one mip level of an embedded texture, or `None` for
+    /// missing and external textures.
+    pub fn mip_level(&self, index: usize, level: usize) -> Result<Option<&'a [u8]>, BspParseError> {
+        if level >= MIP_LEVELS {
+            return Err(BspParseError::IndexOutOfRange {
+                kind: "mip level",
+                index: level,
+                len: MIP_LEVELS,
+            });
+        }
+        let Some(data) = self.texture_data(index)? else {
+            return Ok(None);
+        };
+        let mip_tex: BspMipTex = struct_at(data, 0)?;
+        if mip_tex.is_external() {
+            return Ok(None);
+        }
+        let (width, height) = mip_tex.mip_size(level);
+        let offset = mip_tex.n_offsets[level]
+            .try_into()
+            .map_err(BspParseError::BadPointerValue)?;
+        slice_at(data, offset, width * height).map(Some)
+    }
+
+    /// Decodes the given texture to an owned `BspTexture`, or `None` for
+    /// missing textures.
+    pub fn texture(&self, index: usize) -> Result<Option<BspTexture>, BspParseError> {
+        self.texture_data(index)?
+            .map(|data| decode_mip_tex(data, self.version.has_texture_palettes()))
+            .transpose()
+    }
+
+    pub fn vertices(&self) -> Result<&'a [BspVertex], BspParseError> {
+        self.cast_lump(LUMP_VERTICES)
+    }
+
+    /// The compressed VIS data, see `BspVisLump`.
+    pub fn vis(&self) -> Result<&'a [u8], BspParseError> {
+        self.lump(LUMP_VISIBILITY)
+    }
+
+    pub fn nodes(&self) -> Result<&'a [BspNode], BspParseError> {
+        self.cast_lump(LUMP_NODES)
+    }
+
+    pub fn tex_info(&self) -> Result<&'a [TexInfo], BspParseError> {
+        self.cast_lump(LUMP_TEXINFO)
+    }
+
+    pub fn faces(&self) -> Result<&'a [BspFace], BspParseError> {
+        self.cast_lump(LUMP_FACES)
+    }
+
+    /// The lighting samples: RGB triples for BSP30, single bytes for BSP29.
+    pub fn light_map(&self) -> Result<&'a [u8], BspParseError> {
+        self.lump(LUMP_LIGHTING)
+    }
+
+    pub fn clip_nodes(&self) -> Result<&'a [BspClipNode], BspParseError> {
+        self.cast_lump(LUMP_CLIPNODES)
+    }
+
+    pub fn leaves(&self) -> Result<&'a [BspLeaf], BspParseError> {
+        self.cast_lump(LUMP_LEAVES)
+    }
+
+    pub fn mark_surfaces(&self) -> Result<&'a [BspMarkSurface], BspParseError> {
+        self.cast_lump(LUMP_MARKSURFACES)
+    }
+
+    pub fn edges(&self) -> Result<&'a [BspEdge], BspParseError> {
+        self.cast_lump(LUMP_EDGES)
+    }
+
+    pub fn surf_edges(&self) -> Result<&'a [BspSurfEdge], BspParseError> {
+        self.cast_lump(LUMP_SURFEDGES)
+    }
+
+    pub fn models(&self) -> Result<&'a [BspModel], BspParseError> {
+        self.cast_lump(LUMP_MODELS)
+    }
+}
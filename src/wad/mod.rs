@@ -0,0 +1,255 @@
+pub mod resolver;
+
+use std::io::{Read, Seek};
+
+use bytemuck::{try_cast_slice, Pod, Zeroable};
+
+use crate::{
+    header::BspLumpPointer,
+    lumps::textures::{BspPalette, BspTexture, MAX_TEXTURE_NAME},
+    parsing::{
+        decoding::{extract_struct, seek_and_extract, slice_at, struct_at, BspParseError},
+        textures::{decode_mip_tex, decode_palette},
+    },
+};
+
+pub const WAD3_MAGIC: [u8; 4] = *b"WAD3";
+
+pub struct WadEntryType(pub u8);
+pub const TYP_QPIC: WadEntryType = WadEntryType(0x42);
+pub const TYP_MIPTEX: WadEntryType = WadEntryType(0x43);
+pub const TYP_FONT: WadEntryType = WadEntryType(0x46);
+
+pub const NUM_GLYPHS: usize = 256;
+
+/// # WAD3 header
+///
+/// WAD files are plain archives of images, used by GoldSrc for the textures
+/// that are not embedded in the BSP files.
+///
+/// ```c
+/// typedef struct _WADHEADER {
+///     char szMagic[4];    // should be WAD2/WAD3
+///     int32_t nDir;       // number of directory entries
+///     int32_t nDirOffset; // offset into directory
+/// } WADHEADER;
+/// ```
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct WadHeader {
+    pub sz_magic: [u8; 4],
+    pub n_dir: i32,
+    pub n_dir_offset: i32,
+}
+
+/// # WAD3 directory entry
+///
+/// ```c
+/// typedef struct _WADDIRENTRY {
+///     int32_t nFilePos;            // offset in WAD
+///     int32_t nDiskSize;           // size in file
+///     int32_t nSize;               // uncompressed size
+///     int8_t nType;                // type of entry
+///     bool bCompression;           // 0 if none
+///     int16_t nDummy;              // not used
+///     char szName[MAXTEXTURENAME]; // must be null terminated
+/// } WADDIRENTRY;
+/// ```
+///
+/// Compression was never implemented by the tools, so every entry is stored
+/// as is.
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct WadDirEntry {
+    pub n_file_pos: i32,
+    pub n_disk_size: i32,
+    pub n_size: i32,
+    pub n_type: u8,
+    pub b_compression: u8,
+    pub n_dummy: u16,
+    pub sz_name: [u8; MAX_TEXTURE_NAME],
+}
+impl WadDirEntry {
+    pub fn name(&self) -> String {
+        let first = self
+            .sz_name
+            .iter()
+            .position(|&x| x == 0)
+            .unwrap_or(self.sz_name.len());
+        String::from_utf8_lossy(&self.sz_name[..first]).into_owned()
+    }
+}
+impl std::fmt::Debug for WadDirEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WadDirEntry")
+            .field("n_file_pos", &self.n_file_pos)
+            .field("n_disk_size", &self.n_disk_size)
+            .field("n_size", &self.n_size)
+            .field("n_type", &self.n_type)
+            .field("b_compression", &self.b_compression)
+            .field("sz_name", &self.name())
+            .finish()
+    }
+}
+
+/// A `qpic`: a single, unmipped image such as the HUD graphics.
+///
+/// ```c
+/// typedef struct _QPIC {
+///     int32_t nWidth, nHeight;
+///     uint8_t data[nWidth * nHeight];
+///     int16_t nColors; // always 256
+///     uint8_t palette[nColors][3];
+/// } QPIC;
+/// ```
+#[derive(Debug, Clone)]
+pub struct WadPic {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+    pub palette: BspPalette,
+}
+
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct WadCharInfo {
+    pub start_offset: i16,
+    pub char_width: i16,
+}
+
+/// A bitmap font: a 256 pixel wide sheet of glyphs laid out in rows.
+///
+/// ```c
+/// typedef struct _FONT {
+///     int32_t nWidth, nHeight; // width is always 256
+///     int32_t nRowCount, nRowHeight;
+///     CHARINFO fontInfo[256];  // offset and width of each glyph
+///     uint8_t data[nWidth * nHeight];
+///     int16_t nColors;
+///     uint8_t palette[nColors][3];
+/// } FONT;
+/// ```
+#[derive(Debug, Clone)]
+pub struct WadFont {
+    pub width: usize,
+    pub height: usize,
+    pub row_count: usize,
+    pub row_height: usize,
+    pub chars: Vec<WadCharInfo>,
+    pub pixels: Vec<u8>,
+    pub palette: BspPalette,
+}
+
+#[derive(Debug, Clone)]
+pub enum WadLump {
+    MipTex(BspTexture),
+    Pic(WadPic),
+    Font(WadFont),
+    /// Any other entry type, kept as raw bytes.
+    Other(u8, Vec<u8>),
+}
+
+/// # WAD3 archive
+///
+/// Holds the directory of a WAD file. Entries are decoded on demand from the
+/// same reader the directory was extracted from.
+#[derive(Debug)]
+pub struct Wad {
+    pub header: WadHeader,
+    pub entries: Vec<WadDirEntry>,
+}
+
+fn dimension(value: i32) -> Result<usize, BspParseError> {
+    value.try_into().map_err(BspParseError::BadPointerValue)
+}
+
+fn decode_pic(data: &[u8]) -> Result<WadPic, BspParseError> {
+    let [width, height]: [i32; 2] = struct_at(data, 0)?;
+    let (width, height) = (dimension(width)?, dimension(height)?);
+    let pixels = slice_at(data, 8, width * height)?.to_vec();
+    let palette = decode_palette(data, 8 + width * height)?;
+    Ok(WadPic {
+        width,
+        height,
+        pixels,
+        palette,
+    })
+}
+
+fn decode_font(data: &[u8]) -> Result<WadFont, BspParseError> {
+    let [width, height, row_count, row_height]: [i32; 4] = struct_at(data, 0)?;
+    let (width, height) = (dimension(width)?, dimension(height)?);
+    let chars_size = NUM_GLYPHS * std::mem::size_of::<WadCharInfo>();
+    let chars: &[WadCharInfo] = try_cast_slice(slice_at(data, 16, chars_size)?)
+        .map_err(BspParseError::DeserializationError)?;
+    let start = 16 + chars_size;
+    let pixels = slice_at(data, start, width * height)?.to_vec();
+    let palette = decode_palette(data, start + width * height)?;
+    Ok(WadFont {
+        width,
+        height,
+        row_count: dimension(row_count)?,
+        row_height: dimension(row_height)?,
+        chars: chars.to_owned(),
+        pixels,
+        palette,
+    })
+}
+
+impl Wad {
+    /// Extracts the header and the directory of a WAD3 file.
+    pub fn parse<T: Seek + Read>(read: &mut T) -> Result<Self, BspParseError> {
+        let header: WadHeader = extract_struct(read)?;
+        if header.sz_magic != WAD3_MAGIC {
+            return Err(BspParseError::InvalidMagic {
+                valid: WAD3_MAGIC,
+                found: header.sz_magic,
+            });
+        }
+        let ptr = BspLumpPointer {
+            n_offset: header.n_dir_offset,
+            n_length: header
+                .n_dir
+                .saturating_mul(std::mem::size_of::<WadDirEntry>() as i32),
+        };
+        let buffer = seek_and_extract(read, &ptr)?;
+        let entries: &[WadDirEntry] =
+            try_cast_slice(&buffer).map_err(BspParseError::DeserializationError)?;
+        Ok(Wad {
+            header,
+            entries: entries.to_owned(),
+        })
+    }
+
+    /// Finds an entry by name. Names are compared case insensitively, as the
+    /// engine does.
+    pub fn find(&self, name: &str) -> Option<&WadDirEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Reads and decodes the data of one of the entries of this WAD.
+    pub fn extract_entry<T: Seek + Read>(
+        &self,
+        read: &mut T,
+        entry: &WadDirEntry,
+    ) -> Result<WadLump, BspParseError> {
+        if entry.b_compression != 0 {
+            return Err(BspParseError::UnsupportedCompression(entry.b_compression));
+        }
+        let data = seek_and_extract(
+            read,
+            &BspLumpPointer {
+                n_offset: entry.n_file_pos,
+                n_length: entry.n_disk_size,
+            },
+        )?;
+        Ok(match entry.n_type {
+            t if t == TYP_MIPTEX.0 => WadLump::MipTex(decode_mip_tex(&data, true)?),
+            t if t == TYP_QPIC.0 => WadLump::Pic(decode_pic(&data)?),
+            t if t == TYP_FONT.0 => WadLump::Font(decode_font(&data)?),
+            t => WadLump::Other(t, data),
+        })
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    bsp::Bsp,
+    lumps::textures::{BspTexture, BspTextureData},
+    parsing::decoding::BspParseError,
+};
+
+use super::{Wad, WadLump};
+
+/// A texture of the BSP found in one of its WAD files.
+#[derive(Debug)]
+pub struct ResolvedTexture {
+    /// Index of the texture in the texture lump.
+    pub index: usize,
+    /// The WAD file the pixels were read from.
+    pub wad: PathBuf,
+    pub texture: BspTexture,
+}
+
+/// A WAD file that was found but could not be read.
+#[derive(Debug)]
+pub struct FailedWad {
+    pub path: PathBuf,
+    pub error: BspParseError,
+}
+
+#[derive(Debug, Default)]
+pub struct WadResolution {
+    pub textures: Vec<ResolvedTexture>,
+    /// Indices of the external textures that no WAD file provided.
+    pub missing_textures: Vec<usize>,
+    /// Entries of the `wad` key that were not found in any search directory.
+    pub missing_wads: Vec<String>,
+    /// WAD files that were found but are unreadable or corrupt. Their
+    /// textures are looked up in the next files instead.
+    pub failed_wads: Vec<FailedWad>,
+}
+
+/// # WAD resolver
+///
+/// Finds the pixels of the external textures of a map. The WAD files are
+/// taken from the `wad` key of the worldspawn entity, a semicolon separated
+/// list of paths from the machine the map was compiled on, such as
+/// `\half-life\valve\halflife.wad`. Only the file name of each path is kept,
+/// and looked up in the search directories in order.
+#[derive(Debug, Clone, Default)]
+pub struct WadResolver {
+    pub search_dirs: Vec<PathBuf>,
+}
+
+impl WadResolver {
+    pub fn new<P: Into<PathBuf>>(search_dirs: impl IntoIterator<Item = P>) -> Self {
+        Self {
+            search_dirs: search_dirs.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// The WAD files listed by the worldspawn entity, in order.
+    pub fn wad_list(bsp: &Bsp) -> Vec<String> {
+        let worldspawn = bsp.entities.0.iter().find(|entity| {
+            entity
+                .0
+                .iter()
+                .any(|(key, value)| key == "classname" && value == "worldspawn")
+        });
+        let Some((_, wads)) =
+            worldspawn.and_then(|entity| entity.0.iter().find(|(key, _)| key == "wad"))
+        else {
+            return vec![];
+        };
+        wads.to_str()
+            .split(';')
+            .map(str::trim)
+            .filter(|wad| !wad.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    /// Looks a WAD path up in the search directories by its file name. The
+    /// exact name is tried first, then a case insensitive match.
+    pub fn locate(&self, wad: &str) -> Option<PathBuf> {
+        let file_name = wad.rsplit(['/', '\\']).next()?;
+        for dir in &self.search_dirs {
+            let candidate = dir.join(file_name);
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            let found = entries.flatten().find(|entry| {
+                entry
+                    .file_name()
+                    .to_string_lossy()
+                    .eq_ignore_ascii_case(file_name)
+            });
+            if let Some(entry) = found {
+                return Some(entry.path());
+            }
+        }
+        None
+    }
+
+    /// Reads the pixels of every external texture of the map. When several
+    /// WAD files provide the same texture, the first one listed wins. Every
+    /// listed WAD file is looked at, even once all textures are found, so
+    /// that the missing and broken ones are all reported.
+    pub fn resolve(&self, bsp: &Bsp) -> WadResolution {
+        let mut resolution = WadResolution::default();
+        let mut pending: Vec<usize> = bsp
+            .textures
+            .0
+            .iter()
+            .enumerate()
+            .filter(|(_, texture)| matches!(texture.data, BspTextureData::External))
+            .map(|(index, _)| index)
+            .collect();
+        for wad in Self::wad_list(bsp) {
+            let Some(path) = self.locate(&wad) else {
+                resolution.missing_wads.push(wad);
+                continue;
+            };
+            match Self::resolve_from(bsp, &path, &pending) {
+                Ok(textures) => {
+                    pending.retain(|&index| textures.iter().all(|found| found.index != index));
+                    resolution.textures.extend(textures);
+                }
+                Err(error) => resolution.failed_wads.push(FailedWad { path, error }),
+            }
+        }
+        resolution.missing_textures = pending;
+        resolution
+    }
+
+    /// The textures of `pending` found in a WAD file, nothing being kept if
+    /// any of them can't be read.
+    fn resolve_from(
+        bsp: &Bsp,
+        path: &Path,
+        pending: &[usize],
+    ) -> Result<Vec<ResolvedTexture>, BspParseError> {
+        let mut file = BufReader::new(File::open(path).map_err(BspParseError::GenericError)?);
+        let wad = Wad::parse(&mut file)?;
+        let mut textures = vec![];
+        for &index in pending {
+            let Some(entry) = wad.find(&bsp.textures[index].name()) else {
+                continue;
+            };
+            if let WadLump::MipTex(texture) = wad.extract_entry(&mut file, entry)? {
+                textures.push(ResolvedTexture {
+                    index,
+                    wad: path.to_owned(),
+                    texture,
+                });
+            }
+        }
+        Ok(textures)
+    }
+}
@@ -0,0 +1,245 @@
+use bytemuck::{bytes_of, cast_slice, Pod};
+
+use crate::{
+    header::BspVersion,
+    lumps::{
+        clip_nodes::BspClipNodesLump,
+        entities::{BspEntitiesLump, BspEntityString, BspEntityWriteError, MAX_KEY, MAX_VALUE},
+        faces::BspFacesLump,
+        leaves::BspLeavesLump,
+        light_map::BspLightMapLump,
+        models::BspModelsLump,
+        nodes::BspNodesLump,
+        planes::BspPlanesLump,
+        surfaces::{BspEdgesLump, BspMarkSurfacesLump, BspSurfEdgesLump},
+        tex_info::BspTexInfoLump,
+        textures::{BspTexture, BspTextureData, BspTexturesLump, MIP_LEVELS},
+        vertices::BspVerticesLump,
+        vis::BspVisLump,
+    },
+};
+
+/// The inverse of `PtrLumpReader`: turns a lump back into the bytes stored in
+/// a file of the given version.
+pub trait LumpEncoder {
+    fn encode(&self, version: BspVersion) -> Vec<u8>;
+}
+
+fn encode_slice<T: Pod>(values: &[T]) -> Vec<u8> {
+    cast_slice(values).to_vec()
+}
+
+impl BspEntitiesLump {
+    /// The entity text the way the compilers write it, one key per line,
+    /// terminated by a NUL byte.
+    ///
+    /// There are no escape sequences, so a key or a value holding a quote, a
+    /// newline or a NUL can't be written. Neither can keys longer than
+    /// `MAX_KEY` or values longer than `MAX_VALUE`, which the compilers and
+    /// the engine don't read.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BspEntityWriteError> {
+        for (entity, pairs) in self.0.iter().enumerate() {
+            for (key, value) in &pairs.0 {
+                check_pair(entity, key, value)?;
+            }
+        }
+        Ok(self.text())
+    }
+
+    /// The text `to_bytes` writes, without checking that it reads back.
+    fn text(&self) -> Vec<u8> {
+        let mut out = vec![];
+        for entity in &self.0 {
+            out.extend_from_slice(b"{\n");
+            for (key, value) in &entity.0 {
+                out.push(b'"');
+                out.extend_from_slice(key.as_bytes());
+                out.extend_from_slice(b"\" \"");
+                out.extend_from_slice(value.as_bytes());
+                out.extend_from_slice(b"\"\n");
+            }
+            out.extend_from_slice(b"}\n");
+        }
+        out.push(0);
+        out
+    }
+
+    /// Length of the text `to_bytes` writes, whether it can be or not.
+    pub(crate) fn text_len(&self) -> usize {
+        self.text().len()
+    }
+}
+
+fn check_pair(
+    entity: usize,
+    key: &BspEntityString,
+    value: &BspEntityString,
+) -> Result<(), BspEntityWriteError> {
+    let bad = key
+        .as_bytes()
+        .iter()
+        .chain(value.as_bytes())
+        .find(|byte| matches!(byte, b'"' | b'\n' | b'\0'));
+    if let Some(&byte) = bad {
+        return Err(BspEntityWriteError::BadCharacter {
+            entity,
+            key: key.to_string(),
+            byte,
+        });
+    }
+    if key.len() > MAX_KEY {
+        return Err(BspEntityWriteError::KeyLength {
+            entity,
+            key: key.to_string(),
+            found: key.len(),
+        });
+    }
+    if value.len() > MAX_VALUE {
+        return Err(BspEntityWriteError::ValueLength {
+            entity,
+            key: key.to_string(),
+            found: value.len(),
+        });
+    }
+    Ok(())
+}
+
+impl LumpEncoder for BspPlanesLump {
+    fn encode(&self, _: BspVersion) -> Vec<u8> {
+        encode_slice(&self.0)
+    }
+}
+
+/// Encodes a single texture at the start of the returned buffer, followed by
+/// its palette and padding.
+pub fn encode_mip_tex(texture: &BspTexture) -> Vec<u8> {
+    let mut out = bytes_of(&texture.mip_tex).to_vec();
+    if let BspTextureData::Embedded(pixels) = &texture.data {
+        for level in 0..MIP_LEVELS {
+            let offset = texture.mip_tex.n_offsets[level].max(0) as usize;
+            let mip = &pixels.mips[level];
+            if out.len() < offset + mip.len() {
+                out.resize(offset + mip.len(), 0);
+            }
+            out[offset..offset + mip.len()].copy_from_slice(mip);
+        }
+        if let Some(palette) = &pixels.palette {
+            out.extend_from_slice(bytes_of(&(palette.0.len() as u16)));
+            out.extend(palette.0.iter().flatten());
+        }
+    }
+    out.extend_from_slice(&texture.padding);
+    out
+}
+
+/// Textures are laid out one after the other, in the order of the offset
+/// table. Missing textures get a -1 offset and no data.
+impl LumpEncoder for BspTexturesLump {
+    fn encode(&self, _: BspVersion) -> Vec<u8> {
+        let mut data = vec![];
+        let mut offsets = Vec::with_capacity(self.0.len());
+        let base = 4 + self.0.len() * 4;
+        for texture in &self.0 {
+            if let BspTextureData::Missing = texture.data {
+                offsets.push(-1i32);
+                continue;
+            }
+            offsets.push((base + data.len()) as i32);
+            data.extend(encode_mip_tex(texture));
+        }
+        let mut out = bytes_of(&(self.0.len() as i32)).to_vec();
+        out.extend_from_slice(cast_slice(&offsets));
+        out.extend(data);
+        out
+    }
+}
+
+impl LumpEncoder for BspVerticesLump {
+    fn encode(&self, _: BspVersion) -> Vec<u8> {
+        encode_slice(&self.0)
+    }
+}
+
+impl LumpEncoder for BspVisLump {
+    fn encode(&self, _: BspVersion) -> Vec<u8> {
+        self.0.clone()
+    }
+}
+
+impl LumpEncoder for BspNodesLump {
+    fn encode(&self, _: BspVersion) -> Vec<u8> {
+        encode_slice(&self.0)
+    }
+}
+
+impl LumpEncoder for BspTexInfoLump {
+    fn encode(&self, _: BspVersion) -> Vec<u8> {
+        encode_slice(&self.0)
+    }
+}
+
+/// BSP29 lightmap offsets are scaled back to count mono samples.
+impl LumpEncoder for BspFacesLump {
+    fn encode(&self, version: BspVersion) -> Vec<u8> {
+        if !version.has_mono_lighting() {
+            return encode_slice(&self.0);
+        }
+        let mut faces = self.0.clone();
+        for face in faces.iter_mut().filter(|face| face.n_lightmap_offset >= 0) {
+            face.n_lightmap_offset /= 3;
+        }
+        encode_slice(&faces)
+    }
+}
+
+/// BSP29 lighting is written back with one byte per sample, the average of
+/// its channels. Parsed lighting has the same value in all three, which is
+/// kept as is, but colors set afterwards are turned to gray.
+impl LumpEncoder for BspLightMapLump {
+    fn encode(&self, version: BspVersion) -> Vec<u8> {
+        if version.has_mono_lighting() {
+            return self
+                .0
+                .iter()
+                .map(|sample| ((sample.0 as u16 + sample.1 as u16 + sample.2 as u16) / 3) as u8)
+                .collect();
+        }
+        encode_slice(&self.0)
+    }
+}
+
+impl LumpEncoder for BspClipNodesLump {
+    fn encode(&self, _: BspVersion) -> Vec<u8> {
+        encode_slice(&self.0)
+    }
+}
+
+impl LumpEncoder for BspLeavesLump {
+    fn encode(&self, _: BspVersion) -> Vec<u8> {
+        encode_slice(&self.0)
+    }
+}
+
+impl LumpEncoder for BspMarkSurfacesLump {
+    fn encode(&self, _: BspVersion) -> Vec<u8> {
+        encode_slice(&self.0)
+    }
+}
+
+impl LumpEncoder for BspEdgesLump {
+    fn encode(&self, _: BspVersion) -> Vec<u8> {
+        encode_slice(&self.0)
+    }
+}
+
+impl LumpEncoder for BspSurfEdgesLump {
+    fn encode(&self, _: BspVersion) -> Vec<u8> {
+        encode_slice(&self.0)
+    }
+}
+
+impl LumpEncoder for BspModelsLump {
+    fn encode(&self, _: BspVersion) -> Vec<u8> {
+        encode_slice(&self.0)
+    }
+}
@@ -0,0 +1,167 @@
+pub mod encoding;
+pub mod patch;
+
+use std::io::{Seek, SeekFrom, Write};
+
+use bytemuck::{bytes_of, Zeroable};
+
+use crate::{
+    bsp::Bsp,
+    header::{BspHeader, BspLumpPointer, HEADER_LUMPS},
+    lumps::bspx::{BspxHeader, BspxLumpEntry, BspxLumps, BSPX_LUMP_NAME, BSPX_MAGIC},
+    parsing::decoding::BspParseError,
+};
+use encoding::LumpEncoder;
+
+/// Writes `data` followed by zeros up to the next 4 byte boundary, returning
+/// the number of bytes written.
+fn write_padded<W: Write>(write: &mut W, data: &[u8]) -> Result<usize, BspParseError> {
+    let padding = data.len().next_multiple_of(4) - data.len();
+    write.write_all(data).map_err(BspParseError::GenericError)?;
+    write
+        .write_all(&[0u8; 3][..padding])
+        .map_err(BspParseError::GenericError)?;
+    Ok(data.len() + padding)
+}
+
+fn to_i32(value: usize) -> Result<i32, BspParseError> {
+    value.try_into().map_err(BspParseError::BadPointerValue)
+}
+
+impl Bsp {
+    /// Encodes the 15 standard lumps, in index order. Fails if the entity
+    /// text can't be written, see `BspEntitiesLump::to_bytes`.
+    pub fn encode_lumps(&self) -> Result<[Vec<u8>; HEADER_LUMPS], BspParseError> {
+        let version = self.version;
+        Ok([
+            self.entities
+                .to_bytes()
+                .map_err(BspParseError::EntityLumpWriteError)?,
+            self.planes.encode(version),
+            self.textures.encode(version),
+            self.vertices.encode(version),
+            self.vis.encode(version),
+            self.nodes.encode(version),
+            self.tex_info.encode(version),
+            self.faces.encode(version),
+            self.light_map.encode(version),
+            self.clip_nodes.encode(version),
+            self.leaves.encode(version),
+            self.mark_surfaces.encode(version),
+            self.edges.encode(version),
+            self.surf_edges.encode(version),
+            self.models.encode(version),
+        ])
+    }
+
+    /// # BSP Writing
+    ///
+    /// Lays all the lumps out after the header, each one padded to 4 bytes
+    /// like the compilers do, and writes the BSPX lumps after them. Lumps keep
+    /// the order they had in the parsed file, and the header is rebuilt and
+    /// converted back to `layout`.
+    ///
+    /// Every lump is encoded from its decoded form, so an unmodified `Bsp`
+    /// only gives back the exact file it was parsed from when that file was
+    /// laid out the same way, as the usual compilers do. Otherwise the map
+    /// written is the same, but not its bytes:
+    ///
+    /// - The entity text is written as `"key" "value"` lines, without the
+    ///   comments, carriage returns and spacing of the original.
+    /// - Every lump is padded to 4 bytes, the last one included.
+    /// - Gaps between lumps are dropped and overlapping lumps are written
+    ///   twice.
+    /// - BSPX lumps that failed to read are dropped.
+    ///
+    /// Fails before writing anything if an entity holds a pair the entity
+    /// text can't, see `BspEntitiesLump::to_bytes`.
+    ///
+    /// `Bsp::patch_lump` replaces a lump while leaving every other byte of
+    /// the file as it was.
+    ///
+    /// Offsets are relative to the position of `write` when called. Returns
+    /// the header that was written.
+    pub fn write<W: Write + Seek>(&self, write: &mut W) -> Result<BspHeader, BspParseError> {
+        let start = write
+            .stream_position()
+            .map_err(BspParseError::GenericError)?;
+        let lumps = self.encode_lumps()?;
+        let mut order: Vec<usize> = (0..HEADER_LUMPS).collect();
+        order.sort_by_key(|&i| self.header.lump[i].n_offset);
+
+        let mut header = BspHeader {
+            n_version: self.version.raw(),
+            lump: [BspLumpPointer::zeroed(); HEADER_LUMPS],
+        };
+        write
+            .write_all(bytes_of(&header))
+            .map_err(BspParseError::GenericError)?;
+        let mut position = std::mem::size_of::<BspHeader>();
+        for i in order {
+            header.lump[i] = BspLumpPointer {
+                n_offset: to_i32(position)?,
+                n_length: to_i32(lumps[i].len())?,
+            };
+            position += write_padded(write, &lumps[i])?;
+        }
+        if let Some(bspx) = &self.bspx {
+            write_bspx(write, bspx, position)?;
+        }
+
+        let end = write
+            .stream_position()
+            .map_err(BspParseError::GenericError)?;
+        let header = header.with_layout(self.layout);
+        write
+            .seek(SeekFrom::Start(start))
+            .map_err(BspParseError::GenericError)?;
+        write
+            .write_all(bytes_of(&header))
+            .map_err(BspParseError::GenericError)?;
+        write
+            .seek(SeekFrom::Start(end))
+            .map_err(BspParseError::GenericError)?;
+        Ok(header)
+    }
+}
+
+/// Writes the BSPX header and directory at `position`, which must be 4 byte
+/// aligned, followed by every lump padded to 4 bytes.
+fn write_bspx<W: Write>(
+    write: &mut W,
+    bspx: &BspxLumps,
+    position: usize,
+) -> Result<(), BspParseError> {
+    let header = BspxHeader {
+        sz_magic: BSPX_MAGIC,
+        n_lumps: to_i32(bspx.lumps.len())?,
+    };
+    let mut offset = position
+        + std::mem::size_of::<BspxHeader>()
+        + bspx.lumps.len() * std::mem::size_of::<BspxLumpEntry>();
+    let mut directory = Vec::with_capacity(bspx.lumps.len());
+    for lump in &bspx.lumps {
+        let mut sz_name = [0u8; BSPX_LUMP_NAME];
+        let name = lump.name.as_bytes();
+        let len = name.len().min(BSPX_LUMP_NAME - 1);
+        sz_name[..len].copy_from_slice(&name[..len]);
+        directory.push(BspxLumpEntry {
+            sz_name,
+            n_offset: to_i32(offset)?,
+            n_length: to_i32(lump.data.len())?,
+        });
+        offset += lump.data.len().next_multiple_of(4);
+    }
+    write
+        .write_all(bytes_of(&header))
+        .map_err(BspParseError::GenericError)?;
+    for entry in &directory {
+        write
+            .write_all(bytes_of(entry))
+            .map_err(BspParseError::GenericError)?;
+    }
+    for lump in &bspx.lumps {
+        write_padded(write, &lump.data)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+use bytemuck::bytes_of;
+
+use crate::{
+    bsp::Bsp,
+    header::{BspHeader, LumpType, LUMP_ENTITIES},
+    lumps::{
+        bspx::{BspxHeader, BspxLumpEntry, BSPX_MAGIC},
+        entities::BspEntitiesLump,
+    },
+    parsing::{
+        bspx::bspx_offset,
+        decoding::{struct_at, BspLumpError, BspParseError},
+    },
+};
+
+use super::to_i32;
+
+/// Adds `delta` to an offset, failing if it doesn't fit a pointer anymore.
+fn shift(offset: i32, delta: i64) -> Result<i32, BspParseError> {
+    (offset as i64 + delta)
+        .try_into()
+        .map_err(BspParseError::BadPointerValue)
+}
+
+impl Bsp {
+    /// # Lump patching
+    ///
+    /// Copies the map of `read` to `write` with the contents of `lump`
+    /// replaced by `data`, without decoding anything else. The bytes after
+    /// the lump move by the difference in size, keeping their 4 byte
+    /// alignment, and the offsets of the header and of the BSPX directory
+    /// pointing past the lump are updated. Every other lump is copied as is.
+    ///
+    /// Returns the header that was written.
+    pub fn patch_lump<R: Read + Seek, W: Write>(
+        read: &mut R,
+        write: &mut W,
+        lump: LumpType,
+        data: &[u8],
+    ) -> Result<BspHeader, BspParseError> {
+        read.seek(SeekFrom::Start(0))
+            .map_err(BspParseError::GenericError)?;
+        let mut file = vec![];
+        read.read_to_end(&mut file)
+            .map_err(BspParseError::GenericError)?;
+        let (mut header, layout) = Self::extract_normalized_header(&mut Cursor::new(&file))?;
+        let old = header.lump[lump.0];
+        BspLumpError::check(lump, old, 1, file.len() as u64)?;
+        let start = old.n_offset as usize;
+        let end = start + old.n_length as usize;
+        // The data after the lump starts at the next lump, or after the
+        // padding of this one.
+        let tail = header
+            .lump
+            .iter()
+            .enumerate()
+            .filter(|&(i, ptr)| i != lump.0 && ptr.n_offset as usize >= end)
+            .map(|(_, ptr)| ptr.n_offset as usize)
+            .fold(end.next_multiple_of(4), usize::min)
+            .min(file.len().max(end));
+        let new_end = start + data.len();
+        let new_tail = new_end.next_multiple_of(4);
+        let delta = new_tail as i64 - tail as i64;
+
+        let mut out = Vec::with_capacity(new_tail + file.len() - tail);
+        out.extend_from_slice(&file[..start]);
+        out.extend_from_slice(data);
+        out.resize(new_tail, 0);
+        out.extend_from_slice(&file[tail..]);
+
+        let bspx = bspx_offset(&header) as usize;
+        for (i, ptr) in header.lump.iter_mut().enumerate() {
+            if i == lump.0 {
+                ptr.n_length = to_i32(data.len())?;
+            } else if ptr.n_offset as usize >= end {
+                ptr.n_offset = shift(ptr.n_offset, delta)?;
+            }
+        }
+        if bspx >= tail && file.get(bspx..bspx + 4) == Some(&BSPX_MAGIC[..]) {
+            let bspx_header: BspxHeader = struct_at(&file, bspx)?;
+            let new_bspx = (bspx as i64 + delta) as usize;
+            let directory = std::mem::size_of::<BspxHeader>();
+            let entry_size = std::mem::size_of::<BspxLumpEntry>();
+            for n in 0..bspx_header.n_lumps.max(0) as usize {
+                let at = directory + n * entry_size;
+                let mut entry: BspxLumpEntry = struct_at(&file, bspx + at)?;
+                if entry.n_offset as usize >= tail {
+                    entry.n_offset = shift(entry.n_offset, delta)?;
+                }
+                out[new_bspx + at..new_bspx + at + entry_size].copy_from_slice(bytes_of(&entry));
+            }
+        }
+
+        let header = header.with_layout(layout);
+        out[..std::mem::size_of::<BspHeader>()].copy_from_slice(bytes_of(&header));
+        write.write_all(&out).map_err(BspParseError::GenericError)?;
+        Ok(header)
+    }
+
+    /// # Entity patching
+    ///
+    /// Copies the map of `read` to `write` with its entities replaced, see
+    /// `Bsp::patch_lump`. The geometry of the map is left byte for byte as
+    /// it was. Fails without writing anything if the entities can't be
+    /// written, see `BspEntitiesLump::to_bytes`.
+    pub fn patch_entities<R: Read + Seek, W: Write>(
+        read: &mut R,
+        write: &mut W,
+        entities: &BspEntitiesLump,
+    ) -> Result<BspHeader, BspParseError> {
+        let text = entities
+            .to_bytes()
+            .map_err(BspParseError::EntityLumpWriteError)?;
+        Self::patch_lump(read, write, LUMP_ENTITIES, &text)
+    }
+}